@@ -0,0 +1,79 @@
+use crate::agent::learning::{exploration, q_learn};
+use crate::agent::Agent;
+use crate::enviroment::{
+    discrete::{ActionToken, TaggedDiscrete},
+    Validate,
+};
+use crate::manager::data_collector::mem_buffer::MemBuffer;
+
+use rusty_nn::helpers::AsScalarExt;
+use rusty_nn::network::Network;
+
+use std::marker::PhantomData;
+
+/// A copy of a network which always takes the action with the highest output and never learns.
+/// Outputs past the number of actions, like the value estimate of an actor, are ignored.
+pub struct Frozen<E, N, T, S>
+where
+    E: Validate<Action = TaggedDiscrete>,
+    N: Network,
+    T: Fn(&E) -> S,
+{
+    network: N,
+    token: ActionToken,
+    dueling: bool,
+    mask: bool,
+
+    phantom: PhantomData<*const (E, T, S)>,
+}
+
+impl<E, N, T, S> Frozen<E, N, T, S>
+where
+    E: Validate<Action = TaggedDiscrete>,
+    N: Network,
+    T: Fn(&E) -> S,
+    S: AsRef<[f32]>,
+{
+    /// `dueling` networks are read like in `QAgent`, and with `mask` the agent skips actions which the enviroment doesn't accept
+    pub fn new(network: N, token: ActionToken, dueling: bool, mask: bool) -> Self {
+        Self {
+            network,
+            token,
+            dueling,
+            mask,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<E, N, T, S> Agent for Frozen<E, N, T, S>
+where
+    E: Validate<Action = TaggedDiscrete>,
+    N: Network,
+    T: Fn(&E) -> S,
+    S: AsRef<[f32]>,
+{
+    type Env = E;
+    type Data = MemBuffer<T, E, S, ()>;
+
+    fn action(&mut self, env: &Self::Env, data: &Self::Data) -> <Self::Env as Validate>::Action {
+        let outputs = self.network.predict(data.func()(env).as_ref());
+        let mut values = q_learn::q_values(outputs.as_scalar(), self.dueling);
+        values.truncate(self.token.len());
+
+        let valid = (&self.token)
+            .into_iter()
+            .map(|action| !self.mask || env.validate(action))
+            .collect::<Vec<_>>();
+        let act = exploration::greedy(&values, &valid);
+        self.token
+            .action(act as u32)
+            .expect("Could not create action")
+    }
+
+    fn update(&mut self, _data: &Self::Data) {}
+
+    fn set_player(&mut self, player: u32) {
+        self.token.set_player(player);
+    }
+}
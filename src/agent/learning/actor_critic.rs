@@ -0,0 +1,378 @@
+use std::ops::DerefMut;
+
+use random_fast_rng::FastRng;
+
+use super::policy::{self, Mask, Targets};
+use crate::agent::{frozen::Frozen, Agent, AgentBuilder, Freeze};
+use crate::enviroment::{
+    discrete::{ActionToken, TaggedDiscrete},
+    GetToken, Validate,
+};
+use crate::error::{self, Error, Result};
+use crate::manager::data_collector::{mem_buffer::MemBuffer, rollout::Rollout};
+use crate::misc::random;
+
+use rusty_nn::network::Network;
+use rusty_nn::optimizer::Optimizer;
+use rusty_nn::trainer::{Config, Stochaistic};
+
+use std::marker::PhantomData;
+
+/// Advantage actor-critic agent, which trains on every full rollout using generalized advantage estimation.
+/// Without a separate critic the actor has one extra output after the action scores, which estimates the value of the state.
+pub struct ActorCritic<E, O, T, S>
+where
+    E: Validate<Action = TaggedDiscrete>,
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network,
+    T: Fn(&E) -> S,
+{
+    actor: O,
+    critic: Option<O>,
+    token: ActionToken,
+
+    gamma: f32,
+    lambda: f32,
+    entropy: f32,
+    config: Config,
+    mask: bool,
+    eval: bool,
+
+    rng: FastRng,
+
+    phantom: PhantomData<*const (E, T, S)>,
+}
+
+impl<E, O, T, S> ActorCritic<E, O, T, S>
+where
+    E: Validate<Action = TaggedDiscrete>,
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network,
+    T: Fn(&E) -> S,
+    S: AsRef<[f32]>,
+{
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        actor: O,
+        critic: Option<O>,
+        gamma: f32,
+        lambda: f32,
+        entropy: f32,
+        config: Config,
+        mask: bool,
+        seed: u64,
+        token: ActionToken,
+    ) -> Self {
+        Self {
+            actor,
+            critic,
+            token,
+            gamma,
+            lambda,
+            entropy,
+            config,
+            mask,
+            eval: false,
+            rng: FastRng::seed(seed, 0),
+            phantom: PhantomData,
+        }
+    }
+
+    fn evaluate(&mut self, state: &S) -> (Vec<f32>, f32) {
+        policy::scores_and_value(
+            &mut self.actor,
+            self.critic.as_mut(),
+            self.token.len(),
+            state,
+        )
+    }
+
+    fn train(&mut self, data: &<Self as Agent>::Data) {
+        let (gamma, lambda) = (self.gamma, self.lambda);
+        let steps = policy::rollout_steps(data, gamma, lambda, |state| self.evaluate(state));
+
+        let mut samples = Vec::new();
+        let mut critic_samples = Vec::new();
+        for step in steps {
+            let probs = policy::softmax(&step.scores, step.mask.as_deref());
+            let grad = policy::log_prob_gradient(&probs, step.action, step.advantage);
+            let entropy = policy::entropy_gradient(&probs);
+            let mut targets = step
+                .scores
+                .iter()
+                .zip(grad.iter().zip(entropy))
+                .map(|(s, (g, h))| s + g + self.entropy * h)
+                .collect::<Vec<_>>();
+
+            let ret = step.advantage + step.value;
+            if self.critic.is_some() {
+                critic_samples.push((step.state, vec![ret]));
+            } else {
+                targets.push(ret);
+            }
+            samples.push((step.state, targets));
+        }
+
+        let processor = Targets {
+            samples: &samples,
+            optimizer: &mut self.actor,
+        };
+        Stochaistic::new(self.config.batch_size, self.config.epochs, processor).last();
+
+        if let Some(critic) = &mut self.critic {
+            let processor = Targets {
+                samples: &critic_samples,
+                optimizer: critic,
+            };
+            Stochaistic::new(self.config.batch_size, self.config.epochs, processor).last();
+        }
+    }
+}
+
+impl<E, O, T, S> Agent for ActorCritic<E, O, T, S>
+where
+    E: Validate<Action = TaggedDiscrete>,
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network,
+    T: Fn(&E) -> S,
+    S: AsRef<[f32]>,
+{
+    type Env = E;
+    type Data = Rollout<T, E, S, Mask>;
+
+    fn action(&mut self, env: &Self::Env, data: &Self::Data) -> <Self::Env as Validate>::Action {
+        let mask = if self.mask {
+            Some(policy::valid_actions(&self.token, env))
+        } else {
+            None
+        };
+
+        let (scores, _) = self.evaluate(&data.func()(env));
+        let probs = policy::softmax(&scores, mask.as_deref());
+        let act = if self.eval {
+            policy::argmax(&probs)
+        } else {
+            random::sample(&mut self.rng, &probs)
+        };
+        if let (false, Some(mask), Some(latest)) = (self.eval, mask, data.latest()) {
+            latest.data().set(mask);
+        }
+        self.token
+            .action(act as u32)
+            .expect("Could not create action")
+    }
+
+    fn update(&mut self, data: &Self::Data) {
+        if !self.eval && data.is_full() {
+            self.train(data);
+        }
+    }
+
+    fn set_eval(&mut self, eval: bool) {
+        self.eval = eval;
+    }
+
+    fn set_player(&mut self, player: u32) {
+        self.token.set_player(player);
+    }
+}
+
+impl<E, O, T, S> Freeze for ActorCritic<E, O, T, S>
+where
+    E: Validate<Action = TaggedDiscrete> + 'static,
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network + Clone + 'static,
+    T: Fn(&E) -> S + Clone + 'static,
+    S: AsRef<[f32]> + 'static,
+{
+    type Frozen = Frozen<E, O::Target, T, S>;
+
+    fn freeze(&self, data: &Self::Data) -> (Self::Frozen, <Self::Frozen as Agent>::Data) {
+        let frozen = Frozen::new((*self.actor).clone(), self.token.clone(), false, self.mask);
+        (frozen, MemBuffer::new(1, data.func().clone()))
+    }
+}
+
+pub struct ActorCriticBuilder<E, O, T, S>
+where
+    E: Validate<Action = TaggedDiscrete>,
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network,
+    T: Fn(&E) -> S,
+    S: AsRef<[f32]>,
+{
+    actor: Option<O>,
+    critic: Option<O>,
+    gamma: Option<f32>,
+    lambda: Option<f32>,
+    entropy: f32,
+    config: Option<Config>,
+    mask: bool,
+    seed: u64,
+    len: Option<usize>,
+    inputs: Option<usize>,
+    func: Option<T>,
+    phantom: PhantomData<*const (E, T, S)>,
+}
+
+impl<E, O, T, S> ActorCriticBuilder<E, O, T, S>
+where
+    E: Validate<Action = TaggedDiscrete>,
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network,
+    T: Fn(&E) -> S,
+    S: AsRef<[f32]>,
+{
+    pub fn new() -> Self {
+        Self {
+            actor: None,
+            critic: None,
+            gamma: None,
+            lambda: None,
+            entropy: 0.,
+            config: None,
+            mask: false,
+            seed: 0,
+            len: None,
+            inputs: None,
+            func: None,
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn actor(mut self, actor: O) -> Self {
+        self.actor.replace(actor);
+        self
+    }
+    /// Learn the value of states in a network of its own, whose single output is trained on the GAE returns.
+    /// Without it the actor needs an extra output after the action scores.
+    pub fn critic(mut self, critic: O) -> Self {
+        self.critic.replace(critic);
+        self
+    }
+    pub fn gamma(mut self, gamma: f32) -> Self {
+        self.gamma.replace(gamma);
+        self
+    }
+    pub fn lambda(mut self, lambda: f32) -> Self {
+        self.lambda.replace(lambda);
+        self
+    }
+    /// The weight of the entropy bonus
+    pub fn entropy(mut self, entropy: f32) -> Self {
+        self.entropy = entropy;
+        self
+    }
+    pub fn config(mut self, config: Config) -> Self {
+        self.config.replace(config);
+        self
+    }
+    /// Give actions which the enviroment doesn't accept zero probability, both in rollouts and in the actor's update
+    pub fn mask(mut self, mask: bool) -> Self {
+        self.mask = mask;
+        self
+    }
+    /// Seed of the random generator which samples actions during rollouts
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+    /// The number of transitions in a rollout
+    pub fn len(mut self, len: usize) -> Self {
+        self.len.replace(len);
+        self
+    }
+    pub fn func(mut self, func: T) -> Self {
+        self.func.replace(func);
+        self
+    }
+    /// The size of the states made by `func`, which lets `build` check that the networks have the right number of outputs
+    pub fn inputs(mut self, inputs: usize) -> Self {
+        self.inputs.replace(inputs);
+        self
+    }
+}
+
+impl<E, O, T, S> Default for ActorCriticBuilder<E, O, T, S>
+where
+    E: Validate<Action = TaggedDiscrete>,
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network,
+    T: Fn(&E) -> S,
+    S: AsRef<[f32]>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B, E, O, T, S> AgentBuilder<B> for ActorCriticBuilder<E, O, T, S>
+where
+    E: Validate<Action = TaggedDiscrete>,
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network,
+    T: Fn(&E) -> S,
+    S: AsRef<[f32]>,
+    B: GetToken<Token = ActionToken>,
+{
+    type Data = Rollout<T, E, S, Mask>;
+    type Agent = ActorCritic<E, O, T, S>;
+
+    fn build(self, env: &mut B) -> Result<(Self::Agent, Self::Data)> {
+        let mut actor = self.actor.ok_or(Error::MissingParameter("actor"))?;
+        let gamma = self.gamma.ok_or(Error::MissingParameter("gamma"))?;
+        let lambda = self.lambda.ok_or(Error::MissingParameter("lambda"))?;
+        let config = self.config.ok_or(Error::MissingParameter("config"))?;
+        let len = self.len.ok_or(Error::MissingParameter("len"))?;
+        let func = self.func.ok_or(Error::MissingParameter("func"))?;
+        let inputs = self.inputs.ok_or(Error::MissingParameter("inputs"))?;
+        error::ensure(
+            (0. ..=1.).contains(&gamma),
+            "gamma",
+            "must be between 0 and 1",
+        )?;
+        error::ensure(
+            (0. ..=1.).contains(&lambda),
+            "lambda",
+            "must be between 0 and 1",
+        )?;
+        error::ensure(len > 0, "len", "must be at least 1")?;
+        error::ensure(
+            config.batch_size > 0,
+            "config",
+            "the batch size must be at least 1",
+        )?;
+
+        let mut critic = self.critic;
+        if let Some(critic) = &mut critic {
+            super::check_outputs(&mut **critic, inputs, 1)?;
+        }
+        // without a critic the value of the state is an extra output of the actor
+        let token = super::take_token(env, &mut *actor, inputs, critic.is_none() as usize)?;
+        let agent = ActorCritic::new(
+            actor,
+            critic,
+            gamma,
+            lambda,
+            self.entropy,
+            config,
+            self.mask,
+            self.seed,
+            token,
+        );
+        let data = Rollout::new(len, func);
+        Ok((agent, data))
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+}
@@ -0,0 +1,261 @@
+use std::ops::DerefMut;
+
+use super::{exploration, policy};
+use crate::agent::{Agent, AgentBuilder};
+use crate::enviroment::{
+    discrete::{ActionToken, TaggedDiscrete},
+    GetToken, Validate,
+};
+use crate::error::{self, Error, Result};
+use crate::manager::data_collector::{mem_buffer::MemBuffer, DataPoint, Transition};
+
+use rusty_nn::helpers::AsScalarExt;
+use rusty_nn::network::Network;
+use rusty_nn::optimizer::Optimizer;
+use rusty_nn::trainer::{Config, Processor, Stochaistic};
+
+use std::marker::PhantomData;
+
+/// An agent which learns to imitate the actions taken in recorded episodes.
+/// It doesn't learn from its own experience and always picks the action with the highest score.
+pub struct CloneAgent<E, O, T, S>
+where
+    E: Validate<Action = TaggedDiscrete>,
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network,
+    T: Fn(&E) -> S,
+{
+    optimizer: O,
+    token: ActionToken,
+    config: Config,
+    mask: bool,
+
+    phantom: PhantomData<*const (E, T, S)>,
+}
+
+impl<E, O, T, S> CloneAgent<E, O, T, S>
+where
+    E: Validate<Action = TaggedDiscrete>,
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network,
+    T: Fn(&E) -> S,
+    S: AsRef<[f32]>,
+{
+    fn new(optimizer: O, config: Config, token: ActionToken, mask: bool) -> Self {
+        Self {
+            optimizer,
+            token,
+            config,
+            mask,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Train the network to predict the action taken in each state of the episodes.
+    /// Any iterator over episodes works, like `MemBuffer::episodes` or a slice of `Record`s.
+    /// A `Reader` yields `io::Result`s, so collect its records first:
+    /// `fit(&reader.collect::<io::Result<Vec<_>>>()?)`.
+    pub fn fit<'a, I, X, D>(&mut self, episodes: I)
+    where
+        I: IntoIterator,
+        I::Item: IntoIterator<Item = &'a DataPoint<X, TaggedDiscrete, D>>,
+        X: AsRef<[f32]> + 'a,
+        D: 'a,
+    {
+        let mut pairs = Vec::new();
+        for episode in episodes {
+            let mut prev = None;
+            for data_point in episode {
+                if let (Some(state), Transition::Trans { action, .. }) =
+                    (prev, data_point.transition())
+                {
+                    pairs.push((state, action.action as usize));
+                }
+                prev = Some(data_point.state());
+            }
+        }
+
+        if pairs.is_empty() {
+            return;
+        }
+
+        let processor = Process {
+            pairs: &pairs,
+            outputs: self.token.len(),
+            optimizer: &mut self.optimizer,
+        };
+        let trainer = Stochaistic::new(self.config.batch_size, self.config.epochs, processor);
+        trainer.last();
+    }
+}
+
+struct Process<'a, X, O> {
+    pairs: &'a [(&'a X, usize)],
+    outputs: usize,
+    optimizer: &'a mut O,
+}
+
+impl<'a, X, O> Processor for Process<'a, X, O>
+where
+    X: AsRef<[f32]>,
+    O: Optimizer,
+{
+    fn process(&mut self, idx: usize) -> f32 {
+        let (state, action) = self.pairs[idx];
+        let mut loss = 0.;
+        for i in 0..self.outputs {
+            let target = if i == action { 1. } else { 0. };
+            loss += self.optimizer.process_partial(state.as_ref(), i, target);
+        }
+        loss
+    }
+
+    fn size(&self) -> usize {
+        self.pairs.len()
+    }
+
+    fn end_batch(&mut self, _batch: usize) {
+        self.optimizer.update_model();
+    }
+}
+
+impl<E, O, T, S> Agent for CloneAgent<E, O, T, S>
+where
+    E: Validate<Action = TaggedDiscrete>,
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network,
+    T: Fn(&E) -> S,
+    S: AsRef<[f32]>,
+{
+    type Env = E;
+    type Data = MemBuffer<T, E, S, ()>;
+
+    fn action(&mut self, env: &Self::Env, data: &Self::Data) -> <Self::Env as Validate>::Action {
+        let valid = if self.mask {
+            policy::valid_actions(&self.token, env)
+        } else {
+            vec![true; self.token.len()]
+        };
+        let scores = self.optimizer.predict(data.func()(env).as_ref());
+        let act = exploration::greedy(scores.as_scalar(), &valid);
+        self.token
+            .action(act as u32)
+            .expect("Could not create action")
+    }
+
+    fn update(&mut self, _data: &Self::Data) {}
+
+    fn set_player(&mut self, player: u32) {
+        self.token.set_player(player);
+    }
+}
+
+pub struct CloneBuilder<E, O, T, S>
+where
+    E: Validate<Action = TaggedDiscrete>,
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network,
+    T: Fn(&E) -> S,
+    S: AsRef<[f32]>,
+{
+    optimizer: Option<O>,
+    config: Option<Config>,
+    mask: bool,
+    inputs: Option<usize>,
+    func: Option<T>,
+    phantom: PhantomData<*const (E, T, S)>,
+}
+
+impl<E, O, T, S> CloneBuilder<E, O, T, S>
+where
+    E: Validate<Action = TaggedDiscrete>,
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network,
+    T: Fn(&E) -> S,
+    S: AsRef<[f32]>,
+{
+    pub fn new() -> Self {
+        Self {
+            optimizer: None,
+            config: None,
+            mask: false,
+            inputs: None,
+            func: None,
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn optimizer(mut self, optimizer: O) -> Self {
+        self.optimizer.replace(optimizer);
+        self
+    }
+    pub fn config(mut self, config: Config) -> Self {
+        self.config.replace(config);
+        self
+    }
+    /// Skip actions which the enviroment doesn't accept
+    pub fn mask(mut self, mask: bool) -> Self {
+        self.mask = mask;
+        self
+    }
+    pub fn func(mut self, func: T) -> Self {
+        self.func.replace(func);
+        self
+    }
+    /// The size of the states made by `func`, which lets `build` check that the networks have the right number of outputs
+    pub fn inputs(mut self, inputs: usize) -> Self {
+        self.inputs.replace(inputs);
+        self
+    }
+}
+
+impl<E, O, T, S> Default for CloneBuilder<E, O, T, S>
+where
+    E: Validate<Action = TaggedDiscrete>,
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network,
+    T: Fn(&E) -> S,
+    S: AsRef<[f32]>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B, E, O, T, S> AgentBuilder<B> for CloneBuilder<E, O, T, S>
+where
+    E: Validate<Action = TaggedDiscrete>,
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network,
+    T: Fn(&E) -> S,
+    S: AsRef<[f32]>,
+    B: GetToken<Token = ActionToken>,
+{
+    type Data = MemBuffer<T, E, S, ()>;
+    type Agent = CloneAgent<E, O, T, S>;
+
+    fn build(self, env: &mut B) -> Result<(Self::Agent, Self::Data)> {
+        let mut optimizer = self.optimizer.ok_or(Error::MissingParameter("optimizer"))?;
+        let config = self.config.ok_or(Error::MissingParameter("config"))?;
+        let func = self.func.ok_or(Error::MissingParameter("func"))?;
+        let inputs = self.inputs.ok_or(Error::MissingParameter("inputs"))?;
+        error::ensure(
+            config.batch_size > 0,
+            "config",
+            "the batch size must be at least 1",
+        )?;
+
+        let token = super::take_token(env, &mut *optimizer, inputs, 0)?;
+        let agent = CloneAgent::new(optimizer, config, token, self.mask);
+        // the agent doesn't learn from its own experience, so the buffer only holds the state function
+        let data = MemBuffer::new(1, func);
+        Ok((agent, data))
+    }
+}
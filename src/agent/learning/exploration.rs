@@ -0,0 +1,207 @@
+use random_fast_rng::{FastRng, Random};
+
+use super::policy;
+use crate::misc::{random, Schedule};
+
+/// Picks actions for value based agents from their estimates of the action values.
+pub trait Exploration {
+    /// Returns the index of the chosen action.
+    /// `t` is the number of steps taken by the agent and actions which are `false` in `valid` mustn't be picked.
+    fn select(&mut self, t: usize, values: &[f32], valid: &[bool], rng: &mut FastRng) -> usize;
+
+    /// Returns what the exploration remembers between actions, so that it can be checkpointed.
+    /// Explorations which only depend on `t` have nothing to remember.
+    fn state(&self) -> Vec<usize> {
+        Vec::new()
+    }
+
+    /// Continue from a state returned by `state`
+    fn set_state(&mut self, _state: Vec<usize>) {}
+}
+
+/// Returns the index of the valid action with the highest value
+pub fn greedy(values: &[f32], valid: &[bool]) -> usize {
+    let mut act = None;
+    let mut max = f32::MIN;
+    for (i, f) in values.iter().enumerate() {
+        if valid[i] && (*f > max || act.is_none()) {
+            max = *f;
+            act = Some(i);
+        }
+    }
+    act.expect("No valid action available")
+}
+
+/// Take a uniformly random valid action with the probability of `eps(t)`, otherwise act greedily
+pub struct EpsilonGreedy<F: Schedule> {
+    eps: F,
+}
+
+impl<F: Schedule> EpsilonGreedy<F> {
+    pub fn new(eps: F) -> Self {
+        Self { eps }
+    }
+}
+
+impl<F: Schedule> Exploration for EpsilonGreedy<F> {
+    fn select(&mut self, t: usize, values: &[f32], valid: &[bool], rng: &mut FastRng) -> usize {
+        let eps = self.eps.value(t);
+        let rand = rng.get_u32();
+
+        if rand > (eps * u32::MAX as f32) as u32 {
+            greedy(values, valid)
+        } else {
+            let count = valid.iter().filter(|v| **v).count();
+            let nth = random::below(rng, count);
+            valid
+                .iter()
+                .enumerate()
+                .filter(|(_, v)| **v)
+                .nth(nth)
+                .map(|(i, _)| i)
+                .expect("No valid action available")
+        }
+    }
+}
+
+/// Sample actions from a softmax over the values divided by `temperature(t)`.
+/// The softmax becomes the greedy choice as the temperature falls, so a temperature of zero acts greedily.
+pub struct Boltzmann<F: Schedule> {
+    temperature: F,
+}
+
+impl<F: Schedule> Boltzmann<F> {
+    pub fn new(temperature: F) -> Self {
+        Self { temperature }
+    }
+}
+
+impl<F: Schedule> Exploration for Boltzmann<F> {
+    fn select(&mut self, t: usize, values: &[f32], valid: &[bool], rng: &mut FastRng) -> usize {
+        let temperature = self.temperature.value(t);
+        if temperature <= 0. {
+            return greedy(values, valid);
+        }
+        let scores = values.iter().map(|v| v / temperature).collect::<Vec<_>>();
+        let probs = policy::softmax(&scores, Some(valid));
+        random::sample(rng, &probs)
+    }
+}
+
+/// Act greedily with respect to the values plus a bonus of `c * sqrt(ln(t) / n)`,
+/// where `n` is the number of times the action was already chosen.
+/// The counts don't depend on the state.
+pub struct Ucb {
+    c: f32,
+    counts: Vec<usize>,
+}
+
+impl Ucb {
+    pub fn new(c: f32) -> Self {
+        Self {
+            c,
+            counts: Vec::new(),
+        }
+    }
+}
+
+impl Exploration for Ucb {
+    fn select(&mut self, t: usize, values: &[f32], valid: &[bool], _rng: &mut FastRng) -> usize {
+        if self.counts.len() < values.len() {
+            self.counts.resize(values.len(), 0);
+        }
+
+        let ln = ((t + 1) as f32).ln();
+        let bonus = values
+            .iter()
+            .zip(&self.counts)
+            .map(|(v, n)| v + self.c * (ln / (*n + 1) as f32).sqrt())
+            .collect::<Vec<_>>();
+        let act = greedy(&bonus, valid);
+        self.counts[act] += 1;
+        act
+    }
+
+    fn state(&self) -> Vec<usize> {
+        self.counts.clone()
+    }
+
+    fn set_state(&mut self, state: Vec<usize>) {
+        self.counts = state;
+    }
+}
+
+/// Act greedily after adding gaussian noise with the standard deviation of `sigma(t)` to the values
+pub struct NoisyGreedy<F: Schedule> {
+    sigma: F,
+}
+
+impl<F: Schedule> NoisyGreedy<F> {
+    pub fn new(sigma: F) -> Self {
+        Self { sigma }
+    }
+}
+
+impl<F: Schedule> Exploration for NoisyGreedy<F> {
+    fn select(&mut self, t: usize, values: &[f32], valid: &[bool], rng: &mut FastRng) -> usize {
+        let sigma = self.sigma.value(t);
+        let noisy = values
+            .iter()
+            .map(|v| v + sigma * random::normal(rng))
+            .collect::<Vec<_>>();
+        greedy(&noisy, valid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::misc::schedule::Constant;
+
+    fn rng() -> FastRng {
+        FastRng::seed(7, 0)
+    }
+
+    #[test]
+    fn boltzmann_without_temperature_is_greedy() {
+        let mut boltzmann = Boltzmann::new(Constant(0.));
+        let values = [1., 3., 2.];
+        assert_eq!(boltzmann.select(0, &values, &[true; 3], &mut rng()), 1);
+        assert_eq!(
+            boltzmann.select(0, &values, &[true, false, true], &mut rng()),
+            2
+        );
+    }
+
+    #[test]
+    fn boltzmann_never_picks_masked_actions() {
+        let mut boltzmann = Boltzmann::new(Constant(10.));
+        let mut rng = rng();
+        let mut picked = [0; 3];
+        for t in 0..100 {
+            picked[boltzmann.select(t, &[0., 5., 0.], &[true, false, true], &mut rng)] += 1;
+        }
+        assert_eq!(picked[1], 0);
+        assert!(picked[0] > 0 && picked[2] > 0, "picked {:?}", picked);
+    }
+
+    #[test]
+    fn ucb_tries_every_action_before_repeating_one() {
+        let mut ucb = Ucb::new(1.);
+        let mut picked = (0..3)
+            .map(|t| ucb.select(t, &[0.; 3], &[true; 3], &mut rng()))
+            .collect::<Vec<_>>();
+        picked.sort_unstable();
+        assert_eq!(picked, vec![0, 1, 2]);
+        assert_eq!(ucb.state(), vec![1, 1, 1]);
+    }
+
+    #[test]
+    fn ucb_continues_from_its_state() {
+        let mut ucb = Ucb::new(1.);
+        ucb.set_state(vec![5, 0]);
+        // the bonus of the untried action outweighs the higher value of the other one
+        assert_eq!(ucb.select(5, &[0.5, 0.], &[true; 2], &mut rng()), 1);
+        assert_eq!(ucb.state(), vec![5, 1]);
+    }
+}
@@ -1 +1,61 @@
+pub mod actor_critic;
+pub mod cloning;
+pub mod exploration;
+pub mod policy;
+pub mod ppo;
 pub mod q_learn;
+pub mod reinforce;
+
+use crate::enviroment::{discrete::ActionToken, GetToken};
+use crate::error::{Error, Result};
+
+use rusty_nn::helpers::AsScalarExt;
+use rusty_nn::network::Network;
+use rusty_nn::optimizer::GradientDescent;
+
+/// Optimizers whose learning rate can be changed during training, which lets agents follow a `Schedule`
+pub trait LearningRate {
+    fn set_learning_rate(&mut self, rate: f32);
+}
+
+impl<N: Network> LearningRate for GradientDescent<N> {
+    fn set_learning_rate(&mut self, rate: f32) {
+        GradientDescent::set_learning_rate(self, rate)
+    }
+}
+
+/// Take a token for an agent whose network needs an output for every action and `extra` more.
+/// The token is given back if the network doesn't fit, so that failed builds don't take a player.
+pub(crate) fn take_token<B, N>(
+    env: &mut B,
+    network: &mut N,
+    inputs: usize,
+    extra: usize,
+) -> Result<ActionToken>
+where
+    B: GetToken<Token = ActionToken>,
+    N: Network + ?Sized,
+{
+    let token = env.get_token();
+    match check_outputs(network, inputs, token.len() + extra) {
+        Ok(()) => Ok(token),
+        Err(err) => {
+            env.return_token(token);
+            Err(err)
+        }
+    }
+}
+
+/// Make sure a network has `expected` outputs by feeding it an empty state of `inputs` values
+pub(crate) fn check_outputs<N: Network + ?Sized>(
+    network: &mut N,
+    inputs: usize,
+    expected: usize,
+) -> Result<()> {
+    let found = network.predict(&vec![0.; inputs]).as_scalar().len();
+    if found == expected {
+        Ok(())
+    } else {
+        Err(Error::Dimensions { expected, found })
+    }
+}
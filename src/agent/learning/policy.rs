@@ -0,0 +1,401 @@
+//! Helpers shared by the policy gradient agents.
+//!
+//! The optimizers only know how to move a single output towards a target,
+//! so a gradient step on the policy is expressed as a target for every output,
+//! offset from its current value in the direction of the gradient.
+
+use crate::enviroment::{
+    discrete::{ActionToken, TaggedDiscrete},
+    Validate,
+};
+use crate::manager::data_collector::{rollout::Rollout, DataPoint, Transition};
+
+use rusty_nn::helpers::AsScalarExt;
+use rusty_nn::network::Network;
+use rusty_nn::optimizer::Optimizer;
+use rusty_nn::trainer::Processor;
+
+use std::cell::RefCell;
+use std::ops::DerefMut;
+
+/// Returns which of the actions of the token the enviroment accepts, in the order of the token
+pub fn valid_actions<E>(token: &ActionToken, env: &E) -> Vec<bool>
+where
+    E: Validate<Action = TaggedDiscrete>,
+{
+    token
+        .into_iter()
+        .map(|action| env.validate(action))
+        .collect()
+}
+
+/// The actions which were valid in a state, which agents that mask actions keep in the data point of the state.
+/// Next to the state it stays in step with the transitions, so the update uses the same masked softmax as the sampling.
+#[derive(Clone, Debug, Default)]
+pub struct Mask(RefCell<Option<Vec<bool>>>);
+
+impl Mask {
+    /// Agents only get a shared reference to their data, so the mask is set through one
+    pub fn set(&self, mask: Vec<bool>) {
+        self.0.replace(Some(mask));
+    }
+
+    pub fn get(&self) -> Option<Vec<bool>> {
+        self.0.borrow().clone()
+    }
+}
+
+/// An episode split into its states, the actions taken in them, the rewards which followed
+/// and the masks the actions were picked with. There is one more state than actions, the one after the last action.
+pub struct Trajectory<'a, S> {
+    pub states: Vec<&'a S>,
+    pub actions: Vec<usize>,
+    pub rewards: Vec<f32>,
+    pub masks: Vec<Option<Vec<bool>>>,
+}
+
+impl<'a, S> Trajectory<'a, S> {
+    pub fn new<I>(episode: I) -> Self
+    where
+        I: IntoIterator<Item = &'a DataPoint<S, TaggedDiscrete, Mask>>,
+    {
+        let mut trajectory = Self {
+            states: Vec::new(),
+            actions: Vec::new(),
+            rewards: Vec::new(),
+            masks: Vec::new(),
+        };
+        // the mask of an action is kept with the state it was taken in
+        let mut mask = None;
+        for data_point in episode {
+            if let Transition::Trans { action, reward } = data_point.transition() {
+                trajectory.actions.push(action.action as usize);
+                trajectory.rewards.push(*reward);
+                trajectory.masks.push(mask.take());
+            }
+            trajectory.states.push(data_point.state());
+            mask = data_point.data().get();
+        }
+        trajectory
+    }
+}
+
+/// A step of a rollout with what the policy gradient agents train on
+pub struct Step<'a, S> {
+    pub state: &'a S,
+    pub action: usize,
+    pub mask: Option<Vec<bool>>,
+    /// The action scores of the state before the update
+    pub scores: Vec<f32>,
+    /// The value of the state before the update
+    pub value: f32,
+    pub advantage: f32,
+}
+
+/// Split a rollout into steps and estimate their advantages with `gae`.
+/// `evaluate` returns the action scores and the value of a state.
+/// Episodes which reached a terminal state have the value of zero after their last action.
+pub fn rollout_steps<'a, F, E, S, X>(
+    data: &'a Rollout<F, E, S, Mask>,
+    gamma: f32,
+    lambda: f32,
+    mut evaluate: X,
+) -> Vec<Step<'a, S>>
+where
+    F: Fn(&E) -> S,
+    E: Validate<Action = TaggedDiscrete>,
+    X: FnMut(&S) -> (Vec<f32>, f32),
+{
+    let mut steps = Vec::new();
+    for (i, episode) in data.episodes().enumerate() {
+        let trajectory = Trajectory::new(episode);
+        if trajectory.actions.is_empty() {
+            continue;
+        }
+
+        let (scores, mut values): (Vec<_>, Vec<_>) = trajectory
+            .states
+            .iter()
+            .map(|state| evaluate(state))
+            .unzip();
+        if data.is_terminal(i) {
+            *values.last_mut().unwrap() = 0.;
+        }
+        let advantages = gae(&trajectory.rewards, &values, gamma, lambda);
+
+        let actions = trajectory.actions.into_iter().zip(trajectory.masks);
+        for (t, ((action, mask), scores)) in actions.zip(scores).enumerate() {
+            steps.push(Step {
+                state: trajectory.states[t],
+                action,
+                mask,
+                scores,
+                value: values[t],
+                advantage: advantages[t],
+            });
+        }
+    }
+    steps
+}
+
+/// Returns the `n` action scores of the actor and the value of the state.
+/// The value comes from the critic if there is one, otherwise from the output of the actor after the scores.
+pub fn scores_and_value<O, S>(
+    actor: &mut O,
+    critic: Option<&mut O>,
+    n: usize,
+    state: &S,
+) -> (Vec<f32>, f32)
+where
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network,
+    S: AsRef<[f32]>,
+{
+    let outputs = actor.predict(state.as_ref()).as_scalar().to_vec();
+    match critic {
+        Some(critic) => (outputs, critic.predict(state.as_ref()).as_scalar()[0]),
+        None => (outputs[..n].to_vec(), outputs[n]),
+    }
+}
+
+/// Compute a softmax over the scores. Entries which are `false` in the mask get zero probability.
+/// Panics if the mask doesn't allow any entry, since there is no distribution to sample from.
+pub fn softmax(scores: &[f32], mask: Option<&[bool]>) -> Vec<f32> {
+    let allowed = |i: usize| mask.is_none_or(|mask| mask[i]);
+    assert!((0..scores.len()).any(allowed), "No valid action available");
+
+    let max = scores
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| allowed(*i))
+        .fold(f32::MIN, |max, (_, f)| max.max(*f));
+
+    let mut probs = scores
+        .iter()
+        .enumerate()
+        .map(|(i, f)| if allowed(i) { (f - max).exp() } else { 0. })
+        .collect::<Vec<_>>();
+    let sum: f32 = probs.iter().sum();
+    for p in &mut probs {
+        *p /= sum;
+    }
+    probs
+}
+
+/// Returns the index of the highest score
+pub fn argmax(scores: &[f32]) -> usize {
+    let mut act = 0;
+    let mut max = f32::MIN;
+    for (i, f) in scores.iter().enumerate() {
+        if *f > max {
+            max = *f;
+            act = i;
+        }
+    }
+    act
+}
+
+/// Returns the entropy of a distribution
+pub fn entropy(probs: &[f32]) -> f32 {
+    -probs
+        .iter()
+        .filter(|p| **p > 0.)
+        .map(|p| p * p.ln())
+        .sum::<f32>()
+}
+
+/// Sum the rewards of each step with the discounted rewards which follow it
+pub fn discounted_returns(rewards: &[f32], gamma: f32) -> Vec<f32> {
+    let mut returns = vec![0.; rewards.len()];
+    let mut acc = 0.;
+    for (i, r) in rewards.iter().enumerate().rev() {
+        acc = r + gamma * acc;
+        returns[i] = acc;
+    }
+    returns
+}
+
+/// Generalized advantage estimation.
+/// `values` holds the value of every state of the episode, including the one after the last reward,
+/// which should be zero if the episode has terminated.
+pub fn gae(rewards: &[f32], values: &[f32], gamma: f32, lambda: f32) -> Vec<f32> {
+    assert_eq!(
+        rewards.len() + 1,
+        values.len(),
+        "Expected a value for every state"
+    );
+    let mut advantages = vec![0.; rewards.len()];
+    let mut acc = 0.;
+    for i in (0..rewards.len()).rev() {
+        let delta = rewards[i] + gamma * values[i + 1] - values[i];
+        acc = delta + gamma * lambda * acc;
+        advantages[i] = acc;
+    }
+    advantages
+}
+
+/// Gradient of `weight * ln(probs[action])` with respect to the scores which produced `probs`
+pub fn log_prob_gradient(probs: &[f32], action: usize, weight: f32) -> Vec<f32> {
+    probs
+        .iter()
+        .enumerate()
+        .map(|(i, p)| weight * (if i == action { 1. } else { 0. } - p))
+        .collect()
+}
+
+/// Gradient of the entropy of `probs` with respect to the scores which produced them
+pub fn entropy_gradient(probs: &[f32]) -> Vec<f32> {
+    let h = entropy(probs);
+    probs
+        .iter()
+        .map(|p| if *p > 0. { -p * (p.ln() + h) } else { 0. })
+        .collect()
+}
+
+/// Trains every output of a network towards a precomputed target
+pub struct Targets<'a, X, O> {
+    pub samples: &'a [(&'a X, Vec<f32>)],
+    pub optimizer: &'a mut O,
+}
+
+impl<'a, X, O> Processor for Targets<'a, X, O>
+where
+    X: AsRef<[f32]>,
+    O: Optimizer,
+{
+    fn process(&mut self, idx: usize) -> f32 {
+        let (state, targets) = &self.samples[idx];
+        let mut loss = 0.;
+        for (i, target) in targets.iter().enumerate() {
+            loss += self.optimizer.process_partial(state.as_ref(), i, *target);
+        }
+        loss
+    }
+
+    fn size(&self) -> usize {
+        self.samples.len()
+    }
+
+    fn end_batch(&mut self, _batch: usize) {
+        self.optimizer.update_model();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enviroment::{EnvBuilder, Enviroment};
+    use crate::manager::data_collector::DataCollector;
+    use crate::testing::doubles::{self, Tally, TallyBuilder};
+
+    type Func = fn(&Tally) -> Vec<f32>;
+
+    fn assert_all_close(found: &[f32], expected: &[f32]) {
+        assert_eq!(found.len(), expected.len());
+        for (f, e) in found.iter().zip(expected) {
+            assert!(
+                (f - e).abs() < 1e-5,
+                "expected {:?}, found {:?}",
+                expected,
+                found
+            );
+        }
+    }
+
+    #[test]
+    fn discounted_returns_accumulate_backwards() {
+        let returns = discounted_returns(&[1., 0., 2.], 0.5);
+        assert_all_close(&returns, &[1.5, 1., 2.]);
+        assert!(discounted_returns(&[], 0.9).is_empty());
+    }
+
+    #[test]
+    fn masked_actions_get_no_probability() {
+        let probs = softmax(&[1., 5., 1.], Some(&[true, false, true]));
+        assert_all_close(&probs, &[0.5, 0., 0.5]);
+    }
+
+    #[test]
+    #[should_panic(expected = "No valid action available")]
+    fn a_mask_has_to_allow_some_action() {
+        softmax(&[1., 5.], Some(&[false, false]));
+    }
+
+    #[test]
+    fn gae_without_lambda_is_the_td_error() {
+        let rewards = [1., 0., 2.];
+        let values = [0.5, 1., 0.25, 0.];
+        let advantages = gae(&rewards, &values, 0.9, 0.);
+        assert_all_close(&advantages, &[1.4, -0.775, 1.75]);
+    }
+
+    #[test]
+    fn gae_with_full_lambda_is_the_return_minus_the_value() {
+        let rewards = [1., 0., 2.];
+        let values = [0.5, 1., 0.25, 0.];
+        let returns = discounted_returns(&rewards, 0.9);
+        let expected = returns
+            .iter()
+            .zip(&values)
+            .map(|(r, v)| r - v)
+            .collect::<Vec<_>>();
+        assert_all_close(&gae(&rewards, &values, 0.9, 1.), &expected);
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected a value for every state")]
+    fn gae_needs_the_value_after_the_last_reward() {
+        gae(&[1.], &[0.], 0.9, 0.95);
+    }
+
+    /// Collect an episode of a `Tally` in which only the second action was picked with a mask,
+    /// like after the agent left evaluation mode in the middle of the episode
+    fn rollout(terminal: bool) -> Rollout<Func, Tally, Vec<f32>, Mask> {
+        let mut env = TallyBuilder::new(2).build();
+        let mut rollout: Rollout<Func, Tally, Vec<f32>, Mask> = Rollout::new(8, doubles::state);
+        rollout.begin_episode(&env);
+        for (i, action) in [1, 0].iter().enumerate() {
+            if i == 1 {
+                let latest = rollout.latest().expect("The episode has begun");
+                latest.data().set(vec![false, true]);
+            }
+            let action = TaggedDiscrete {
+                action: *action,
+                player: 0,
+            };
+            env.step(action);
+            rollout.push_result(&env, action, action.action as f32);
+        }
+        if terminal {
+            rollout.end_episode(&env);
+        }
+        rollout
+    }
+
+    #[test]
+    fn masks_stay_with_the_state_they_were_made_in() {
+        let rollout = rollout(false);
+        let episode = rollout.episodes().next().expect("There is an episode");
+        let trajectory = Trajectory::new(episode);
+        assert_eq!(trajectory.states.len(), 3);
+        assert_eq!(trajectory.actions, vec![1, 0]);
+        assert_eq!(trajectory.rewards, vec![1., 0.]);
+        assert_eq!(trajectory.masks, vec![None, Some(vec![false, true])]);
+    }
+
+    #[test]
+    fn terminal_episodes_have_no_value_after_the_last_action() {
+        let evaluate = |_: &Vec<f32>| (vec![0., 0.], 1.);
+        let open = rollout(false);
+        let steps = rollout_steps(&open, 1., 1., evaluate);
+        let advantages = steps.iter().map(|s| s.advantage).collect::<Vec<_>>();
+        assert_all_close(&advantages, &[1., 0.]);
+
+        let terminal = rollout(true);
+        let steps = rollout_steps(&terminal, 1., 1., evaluate);
+        let advantages = steps.iter().map(|s| s.advantage).collect::<Vec<_>>();
+        assert_all_close(&advantages, &[0., -1.]);
+        assert_eq!(steps[1].mask, Some(vec![false, true]));
+    }
+}
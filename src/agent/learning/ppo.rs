@@ -0,0 +1,493 @@
+use std::ops::DerefMut;
+
+use random_fast_rng::FastRng;
+
+use super::policy::{self, Mask};
+use crate::agent::{frozen::Frozen, Agent, AgentBuilder, Freeze};
+use crate::enviroment::{
+    discrete::{ActionToken, TaggedDiscrete},
+    GetToken, Validate,
+};
+use crate::error::{self, Error, Result};
+use crate::manager::data_collector::{mem_buffer::MemBuffer, rollout::Rollout};
+use crate::misc::random;
+
+use rusty_nn::network::Network;
+use rusty_nn::optimizer::Optimizer;
+use rusty_nn::trainer::{Config, Processor, Stochaistic};
+
+use std::marker::PhantomData;
+
+/// Proximal policy optimization agent.
+/// Every full rollout is trained on for `config.epochs` epochs in minibatches of `config.batch_size`,
+/// using the clipped surrogate objective and advantages normalized over the rollout.
+/// Without a separate critic the actor has one extra output after the action scores, which estimates the value of the state.
+pub struct Ppo<E, O, T, S>
+where
+    E: Validate<Action = TaggedDiscrete>,
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network,
+    T: Fn(&E) -> S,
+{
+    actor: O,
+    critic: Option<O>,
+    token: ActionToken,
+
+    gamma: f32,
+    lambda: f32,
+    clip: f32,
+    value_clip: Option<f32>,
+    entropy: f32,
+    config: Config,
+    mask: bool,
+    eval: bool,
+
+    rng: FastRng,
+
+    phantom: PhantomData<*const (E, T, S)>,
+}
+
+impl<E, O, T, S> Ppo<E, O, T, S>
+where
+    E: Validate<Action = TaggedDiscrete>,
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network,
+    T: Fn(&E) -> S,
+    S: AsRef<[f32]>,
+{
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        actor: O,
+        critic: Option<O>,
+        gamma: f32,
+        lambda: f32,
+        clip: f32,
+        value_clip: Option<f32>,
+        entropy: f32,
+        config: Config,
+        mask: bool,
+        seed: u64,
+        token: ActionToken,
+    ) -> Self {
+        Self {
+            actor,
+            critic,
+            token,
+            gamma,
+            lambda,
+            clip,
+            value_clip,
+            entropy,
+            config,
+            mask,
+            eval: false,
+            rng: FastRng::seed(seed, 0),
+            phantom: PhantomData,
+        }
+    }
+
+    fn train(&mut self, data: &<Self as Agent>::Data) {
+        let n = self.token.len();
+        let (actor, critic) = (&mut self.actor, &mut self.critic);
+        let steps = policy::rollout_steps(data, self.gamma, self.lambda, |state| {
+            policy::scores_and_value(actor, critic.as_mut(), n, state)
+        });
+
+        let mut samples = steps
+            .into_iter()
+            .map(|step| Sample {
+                state: step.state,
+                action: step.action,
+                prob: policy::softmax(&step.scores, step.mask.as_deref())[step.action],
+                mask: step.mask,
+                value: step.value,
+                advantage: step.advantage,
+                ret: step.advantage + step.value,
+            })
+            .collect::<Vec<_>>();
+        if samples.is_empty() {
+            return;
+        }
+
+        let mean = samples.iter().map(|s| s.advantage).sum::<f32>() / samples.len() as f32;
+        let var = samples
+            .iter()
+            .map(|s| (s.advantage - mean).powi(2))
+            .sum::<f32>()
+            / samples.len() as f32;
+        let std = var.sqrt() + 1e-8;
+        for sample in &mut samples {
+            sample.advantage = (sample.advantage - mean) / std;
+        }
+
+        let processor = Process {
+            samples: &samples,
+            actor: &mut self.actor,
+            critic: self.critic.as_mut(),
+            outputs: n,
+            clip: self.clip,
+            value_clip: self.value_clip,
+            entropy: self.entropy,
+        };
+        Stochaistic::new(self.config.batch_size, self.config.epochs, processor).last();
+    }
+}
+
+struct Sample<'a, S> {
+    state: &'a S,
+    action: usize,
+    mask: Option<Vec<bool>>,
+    /// The probability of the action under the behaviour policy
+    prob: f32,
+    value: f32,
+    advantage: f32,
+    ret: f32,
+}
+
+struct Process<'a, S, O> {
+    samples: &'a [Sample<'a, S>],
+    actor: &'a mut O,
+    critic: Option<&'a mut O>,
+    outputs: usize,
+    clip: f32,
+    value_clip: Option<f32>,
+    entropy: f32,
+}
+
+impl<'a, S, O> Processor for Process<'a, S, O>
+where
+    S: AsRef<[f32]>,
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network,
+{
+    fn process(&mut self, idx: usize) -> f32 {
+        let sample = &self.samples[idx];
+        let state = sample.state.as_ref();
+        let (scores, value) = policy::scores_and_value(
+            &mut *self.actor,
+            self.critic.as_deref_mut(),
+            self.outputs,
+            &sample.state,
+        );
+
+        let probs = policy::softmax(&scores, sample.mask.as_deref());
+        let ratio = probs[sample.action] / sample.prob;
+        // the gradient vanishes once the ratio leaves the trusted region in the direction of the advantage
+        let clipped = (sample.advantage > 0. && ratio > 1. + self.clip)
+            || (sample.advantage < 0. && ratio < 1. - self.clip);
+        let weight = if clipped {
+            0.
+        } else {
+            ratio * sample.advantage
+        };
+        let grad = policy::log_prob_gradient(&probs, sample.action, weight);
+        let entropy = policy::entropy_gradient(&probs);
+
+        let mut loss = 0.;
+        for (i, (s, (g, h))) in scores.iter().zip(grad.iter().zip(entropy)).enumerate() {
+            let target = s + g + self.entropy * h;
+            loss += self.actor.process_partial(state, i, target);
+        }
+
+        let target = match self.value_clip {
+            Some(clip) => {
+                let clipped = sample.value + (value - sample.value).max(-clip).min(clip);
+                if (clipped - sample.ret).powi(2) > (value - sample.ret).powi(2) {
+                    value
+                } else {
+                    sample.ret
+                }
+            }
+            None => sample.ret,
+        };
+        loss += match &mut self.critic {
+            Some(critic) => critic.process_partial(state, 0, target),
+            None => self.actor.process_partial(state, self.outputs, target),
+        };
+        loss
+    }
+
+    fn size(&self) -> usize {
+        self.samples.len()
+    }
+
+    fn end_batch(&mut self, _batch: usize) {
+        self.actor.update_model();
+        if let Some(critic) = &mut self.critic {
+            critic.update_model();
+        }
+    }
+}
+
+impl<E, O, T, S> Agent for Ppo<E, O, T, S>
+where
+    E: Validate<Action = TaggedDiscrete>,
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network,
+    T: Fn(&E) -> S,
+    S: AsRef<[f32]>,
+{
+    type Env = E;
+    type Data = Rollout<T, E, S, Mask>;
+
+    fn action(&mut self, env: &Self::Env, data: &Self::Data) -> <Self::Env as Validate>::Action {
+        let mask = if self.mask {
+            Some(policy::valid_actions(&self.token, env))
+        } else {
+            None
+        };
+
+        let (scores, _) = policy::scores_and_value(
+            &mut self.actor,
+            self.critic.as_mut(),
+            self.token.len(),
+            &data.func()(env),
+        );
+        let probs = policy::softmax(&scores, mask.as_deref());
+        let act = if self.eval {
+            policy::argmax(&probs)
+        } else {
+            random::sample(&mut self.rng, &probs)
+        };
+        if let (false, Some(mask), Some(latest)) = (self.eval, mask, data.latest()) {
+            latest.data().set(mask);
+        }
+        self.token
+            .action(act as u32)
+            .expect("Could not create action")
+    }
+
+    fn update(&mut self, data: &Self::Data) {
+        if !self.eval && data.is_full() {
+            self.train(data);
+        }
+    }
+
+    fn set_eval(&mut self, eval: bool) {
+        self.eval = eval;
+    }
+
+    fn set_player(&mut self, player: u32) {
+        self.token.set_player(player);
+    }
+}
+
+impl<E, O, T, S> Freeze for Ppo<E, O, T, S>
+where
+    E: Validate<Action = TaggedDiscrete> + 'static,
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network + Clone + 'static,
+    T: Fn(&E) -> S + Clone + 'static,
+    S: AsRef<[f32]> + 'static,
+{
+    type Frozen = Frozen<E, O::Target, T, S>;
+
+    fn freeze(&self, data: &Self::Data) -> (Self::Frozen, <Self::Frozen as Agent>::Data) {
+        let frozen = Frozen::new((*self.actor).clone(), self.token.clone(), false, self.mask);
+        (frozen, MemBuffer::new(1, data.func().clone()))
+    }
+}
+
+pub struct PpoBuilder<E, O, T, S>
+where
+    E: Validate<Action = TaggedDiscrete>,
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network,
+    T: Fn(&E) -> S,
+    S: AsRef<[f32]>,
+{
+    actor: Option<O>,
+    critic: Option<O>,
+    gamma: Option<f32>,
+    lambda: Option<f32>,
+    clip: Option<f32>,
+    value_clip: Option<f32>,
+    entropy: f32,
+    config: Option<Config>,
+    mask: bool,
+    seed: u64,
+    len: Option<usize>,
+    inputs: Option<usize>,
+    func: Option<T>,
+    phantom: PhantomData<*const (E, T, S)>,
+}
+
+impl<E, O, T, S> PpoBuilder<E, O, T, S>
+where
+    E: Validate<Action = TaggedDiscrete>,
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network,
+    T: Fn(&E) -> S,
+    S: AsRef<[f32]>,
+{
+    pub fn new() -> Self {
+        Self {
+            actor: None,
+            critic: None,
+            gamma: None,
+            lambda: None,
+            clip: None,
+            value_clip: None,
+            entropy: 0.,
+            config: None,
+            mask: false,
+            seed: 0,
+            len: None,
+            inputs: None,
+            func: None,
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn actor(mut self, actor: O) -> Self {
+        self.actor.replace(actor);
+        self
+    }
+    /// Estimate values with a separate critic network with a single output, which is what `value_clip` clips.
+    /// Without it the values come from an extra output of the actor, so both share the minibatch updates.
+    pub fn critic(mut self, critic: O) -> Self {
+        self.critic.replace(critic);
+        self
+    }
+    pub fn gamma(mut self, gamma: f32) -> Self {
+        self.gamma.replace(gamma);
+        self
+    }
+    pub fn lambda(mut self, lambda: f32) -> Self {
+        self.lambda.replace(lambda);
+        self
+    }
+    /// How far the probability ratio may move from 1 before its gradient is cut off
+    pub fn clip(mut self, clip: f32) -> Self {
+        self.clip.replace(clip);
+        self
+    }
+    /// How far the value estimate may move from its value at the start of the update
+    pub fn value_clip(mut self, value_clip: f32) -> Self {
+        self.value_clip.replace(value_clip);
+        self
+    }
+    /// The weight of the entropy bonus
+    pub fn entropy(mut self, entropy: f32) -> Self {
+        self.entropy = entropy;
+        self
+    }
+    pub fn config(mut self, config: Config) -> Self {
+        self.config.replace(config);
+        self
+    }
+    /// Leave actions which the enviroment doesn't accept out of the softmax,
+    /// for the behaviour policy as well as the probability ratio of the surrogate objective
+    pub fn mask(mut self, mask: bool) -> Self {
+        self.mask = mask;
+        self
+    }
+    /// Seed of the random generator which samples the actions of the behaviour policy
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+    /// The number of transitions in a rollout
+    pub fn len(mut self, len: usize) -> Self {
+        self.len.replace(len);
+        self
+    }
+    pub fn func(mut self, func: T) -> Self {
+        self.func.replace(func);
+        self
+    }
+    /// The size of the states made by `func`, which lets `build` check that the networks have the right number of outputs
+    pub fn inputs(mut self, inputs: usize) -> Self {
+        self.inputs.replace(inputs);
+        self
+    }
+}
+
+impl<E, O, T, S> Default for PpoBuilder<E, O, T, S>
+where
+    E: Validate<Action = TaggedDiscrete>,
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network,
+    T: Fn(&E) -> S,
+    S: AsRef<[f32]>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B, E, O, T, S> AgentBuilder<B> for PpoBuilder<E, O, T, S>
+where
+    E: Validate<Action = TaggedDiscrete>,
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network,
+    T: Fn(&E) -> S,
+    S: AsRef<[f32]>,
+    B: GetToken<Token = ActionToken>,
+{
+    type Data = Rollout<T, E, S, Mask>;
+    type Agent = Ppo<E, O, T, S>;
+
+    fn build(self, env: &mut B) -> Result<(Self::Agent, Self::Data)> {
+        let mut actor = self.actor.ok_or(Error::MissingParameter("actor"))?;
+        let gamma = self.gamma.ok_or(Error::MissingParameter("gamma"))?;
+        let lambda = self.lambda.ok_or(Error::MissingParameter("lambda"))?;
+        let clip = self.clip.ok_or(Error::MissingParameter("clip"))?;
+        let config = self.config.ok_or(Error::MissingParameter("config"))?;
+        let len = self.len.ok_or(Error::MissingParameter("len"))?;
+        let func = self.func.ok_or(Error::MissingParameter("func"))?;
+        let inputs = self.inputs.ok_or(Error::MissingParameter("inputs"))?;
+        error::ensure(
+            (0. ..=1.).contains(&gamma),
+            "gamma",
+            "must be between 0 and 1",
+        )?;
+        error::ensure(
+            (0. ..=1.).contains(&lambda),
+            "lambda",
+            "must be between 0 and 1",
+        )?;
+        error::ensure(clip > 0., "clip", "must be positive")?;
+        error::ensure(len > 0, "len", "must be at least 1")?;
+        error::ensure(
+            config.batch_size > 0,
+            "config",
+            "the batch size must be at least 1",
+        )?;
+
+        let mut critic = self.critic;
+        if let Some(critic) = &mut critic {
+            super::check_outputs(&mut **critic, inputs, 1)?;
+        }
+        // without a critic the value of the state is an extra output of the actor
+        let token = super::take_token(env, &mut *actor, inputs, critic.is_none() as usize)?;
+        let agent = Ppo::new(
+            actor,
+            critic,
+            gamma,
+            lambda,
+            clip,
+            self.value_clip,
+            self.entropy,
+            config,
+            self.mask,
+            self.seed,
+            token,
+        );
+        let data = Rollout::new(len, func);
+        Ok((agent, data))
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+}
@@ -1,27 +1,47 @@
-use std::ops::{Deref, DerefMut};
+use std::ops::DerefMut;
 
-use random_fast_rng::{FastRng, Random};
+use random_fast_rng::FastRng;
 
+use super::exploration::{self, EpsilonGreedy, Exploration};
+use super::LearningRate;
 use crate::enviroment::{
     discrete::{ActionToken, TaggedDiscrete},
-    Enviroment, GetToken,
+    GetToken, Validate,
 };
-use crate::manager::data_collector::{mem_buffer::MemBuffer, DataCollector, DataPoint, Transition};
+use crate::manager::data_collector::{
+    mem_buffer::{MemBuffer, Snapshot},
+    Transition,
+};
+use crate::misc::{random, Schedule, Seeder};
 use crate::{
-    agent::{Agent, AgentBuilder},
-    enviroment::discrete,
+    agent::{frozen::Frozen, Agent, AgentBuilder, Freeze},
+    error::{self, Error, Result},
 };
 
 use rusty_nn::helpers::AsScalarExt;
 use rusty_nn::network::Network;
 use rusty_nn::optimizer::Optimizer;
-use rusty_nn::trainer::{Processor, Stochaistic, GenericProcessor, TwoVecs, Config};
+use rusty_nn::trainer::{Config, Processor, Stochaistic};
 
 use std::marker::PhantomData;
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "record")]
+use {
+    serde::de::DeserializeOwned,
+    std::fs::File,
+    std::io::{self, BufReader, BufWriter, Write},
+    std::path::Path,
+};
+
+/// Computes the target of a transition from its reward and the q-values of the next state
+pub type QTarget = Box<dyn FnMut(f32, &[f32]) -> f32>;
+type SetLearningRate<O> = Box<dyn FnMut(&mut O, usize)>;
+
 pub struct QAgent<E, O, T, D>
 where
-    E: Enviroment<Action = TaggedDiscrete>,
+    E: Validate<Action = TaggedDiscrete>,
     O: Optimizer,
     O: DerefMut,
     O::Target: Network + Clone,
@@ -31,8 +51,10 @@ where
     net2: O::Target,
     token: ActionToken,
 
-    eps: Box<dyn FnMut(usize) -> f32>,
-    q_target: Box<dyn FnMut(f32, &[f32]) -> f32>,
+    exploration: Box<dyn Exploration>,
+    q_target: QTarget,
+    /// Sets the learning rate of the optimizer for the step before every training
+    learning_rate: Option<SetLearningRate<O>>,
 
     t: usize,
     age: usize,
@@ -40,15 +62,19 @@ where
     train_every: usize,
     lag: usize,
     config: Config,
+    dueling: bool,
+    mask: bool,
+    eval: bool,
 
-    rng: FastRng,
+    /// Seeds a fresh rng for every action, so the random state is a single number which can be checkpointed
+    seeder: Seeder,
 
     phantom: PhantomData<*const (E, T, D)>,
 }
 
 impl<E, O, T, S> QAgent<E, O, T, S>
 where
-    E: Enviroment<Action = TaggedDiscrete>,
+    E: Validate<Action = TaggedDiscrete>,
     O: Optimizer,
     O: DerefMut,
     O::Target: Network + Clone,
@@ -56,95 +82,269 @@ where
     T: Fn(&E) -> S,
     S: AsRef<[f32]>,
 {
-    fn new<P, Q>(
+    #[allow(clippy::too_many_arguments)]
+    fn new<Q>(
         optimizer: O,
         train_every: usize,
         lag: usize,
         config: Config,
+        dueling: bool,
+        mask: bool,
+        seed: u64,
         token: ActionToken,
-        eps: P,
+        exploration: Box<dyn Exploration>,
         q: Q,
+        learning_rate: Option<SetLearningRate<O>>,
     ) -> Self
     where
-        P: FnMut(usize) -> f32 + 'static,
         Q: FnMut(f32, &[f32]) -> f32 + 'static,
     {
         Self {
             net2: optimizer.clone(),
             optimizer,
             token,
-            eps: Box::new(eps),
+            exploration,
             q_target: Box::new(q),
+            learning_rate,
             t: 0,
             age: 0,
             train_every,
             lag,
             config,
-            rng: FastRng::seed(0, 0),
+            dueling,
+            mask,
+            eval: false,
+            seeder: Seeder::new(seed),
             phantom: PhantomData,
         }
     }
 
-    fn select_random(&self, env: &E) -> E::Action {
-        todo!()
-    }
-
     fn train(&mut self, data: &<Self as Agent>::Data) {
+        // every transition node is paired with the node before it, which holds the state the action was taken in
+        let mut transitions = Vec::new();
+        let mut idx = 0;
+        for (episode, nodes) in data.episodes().enumerate() {
+            let len = nodes.len();
+            for i in idx + 1..idx + len {
+                let terminal = i + 1 == idx + len && data.is_terminal(episode);
+                transitions.push((i, terminal));
+            }
+            idx += len;
+        }
+        if transitions.is_empty() {
+            return;
+        }
+
+        let mut rng = FastRng::seed(self.seeder.next_seed(), 0);
+        let mut samples = Vec::with_capacity(self.config.batch_size);
+        for _ in 0..self.config.batch_size {
+            let (i, terminal) = transitions[random::below(&mut rng, transitions.len())];
+            let (action, reward) = match data[i].transition() {
+                Transition::Trans { action, reward } => (action.action as usize, *reward),
+                Transition::First { .. } => unreachable!("Expected a transition node"),
+            };
+            let target = if terminal {
+                reward
+            } else {
+                let next = self.target_q_values(data[i].state());
+                (self.q_target)(reward, &next)
+            };
+            samples.push(Target {
+                state: data[i - 1].state(),
+                idx: action,
+                target,
+            });
+        }
+
+        if let Some(learning_rate) = &mut self.learning_rate {
+            learning_rate(&mut self.optimizer, self.t);
+        }
         let processor = Process {
-            buffer: data,
+            samples: &samples,
             optimizer: &mut self.optimizer,
+            dueling: self.dueling,
         };
+        Stochaistic::new(self.config.batch_size, self.config.epochs, processor).last();
+    }
 
-        let trainer = Stochaistic::new(self.config.batch_size, self.config.epochs, processor);
-        trainer.last();
+    /// Copy the online network into the target network
+    fn sync(&mut self) {
+        self.net2 = (*self.optimizer).clone();
+    }
 
-        if data.is_full() && self.t % (self.lag * self.train_every) == 0 {
-            self.sync(data)
+    /// Returns the q-values of the online network
+    pub fn q_values(&mut self, state: &S) -> Vec<f32> {
+        q_values(self.optimizer.predict(state.as_ref()).as_scalar(), self.dueling)
+    }
+
+    /// Returns the q-values of the target network
+    pub fn target_q_values(&mut self, state: &S) -> Vec<f32> {
+        q_values(self.net2.predict(state.as_ref()).as_scalar(), self.dueling)
+    }
+
+    /// Capture the learning state of the agent, optionally including the contents of its buffer.
+    /// Taking a checkpoint doesn't affect the agent, so a restored agent continues exactly like the original.
+    pub fn checkpoint(&self, data: Option<&<Self as Agent>::Data>) -> QCheckpoint<O::Target, S>
+    where
+        S: Clone,
+    {
+        QCheckpoint {
+            network: (*self.optimizer).clone(),
+            target: self.net2.clone(),
+            t: self.t,
+            age: self.age,
+            seeder: self.seeder.clone(),
+            exploration: self.exploration.state(),
+            train_every: self.train_every,
+            lag: self.lag,
+            batch_size: self.config.batch_size,
+            epochs: self.config.epochs,
+            buffer: data.map(MemBuffer::snapshot),
+        }
+    }
+
+    /// Restore the learning state from a checkpoint.
+    /// The closures aren't part of the checkpoint, so the agent should be built with the same configuration as the original.
+    /// Fails without changing the agent if a parameter or the snapshot of the buffer is invalid.
+    pub fn restore(
+        &mut self,
+        checkpoint: QCheckpoint<O::Target, S>,
+        data: &mut <Self as Agent>::Data,
+    ) -> Result<()> {
+        error::ensure(
+            checkpoint.train_every > 0,
+            "train_every",
+            "must be at least 1",
+        )?;
+        error::ensure(checkpoint.lag > 0, "lag", "must be at least 1")?;
+        error::ensure(
+            checkpoint.batch_size > 0,
+            "config",
+            "the batch size must be at least 1",
+        )?;
+        error::ensure(
+            checkpoint.epochs > 0,
+            "config",
+            "the epochs must be at least 1",
+        )?;
+        if let Some(buffer) = checkpoint.buffer {
+            data.restore(buffer)?;
         }
+        *self.optimizer = checkpoint.network;
+        self.net2 = checkpoint.target;
+        self.t = checkpoint.t;
+        self.age = checkpoint.age;
+        self.seeder = checkpoint.seeder;
+        self.exploration.set_state(checkpoint.exploration);
+        self.train_every = checkpoint.train_every;
+        self.lag = checkpoint.lag;
+        self.config.batch_size = checkpoint.batch_size;
+        self.config.epochs = checkpoint.epochs;
+        Ok(())
+    }
+}
+
+/// The persistent state of a `QAgent`.
+/// Only the networks are saved, so the state of the optimizer is lost.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct QCheckpoint<N, S> {
+    pub network: N,
+    pub target: N,
+    pub t: usize,
+    pub age: usize,
+    pub seeder: Seeder,
+    /// The state of the exploration, like the counts of `Ucb`
+    pub exploration: Vec<usize>,
+    pub train_every: usize,
+    pub lag: usize,
+    pub batch_size: usize,
+    pub epochs: usize,
+    pub buffer: Option<Snapshot<S, TaggedDiscrete, ()>>,
+}
+
+#[cfg(feature = "record")]
+impl<N, S> QCheckpoint<N, S> {
+    /// Write the checkpoint to a json file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()>
+    where
+        N: Serialize,
+        S: Serialize,
+    {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut writer, self).map_err(io::Error::from)?;
+        writer.flush()?;
+        Ok(())
+    }
 
-        // for episode in data.episodes() {
-        //     let iter = episode.peekable();
-        //     while let Some(state) = iter.next() {
-        //         if let Some(&DataPoint { state, data, .. }) = iter.peek() {
-                    
-        //         }
-        //     }
-        // }
-        // self.optimizer.process_partial(data, index)
+    /// Read a checkpoint written by `save`
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self>
+    where
+        N: DeserializeOwned,
+        S: DeserializeOwned,
+    {
+        let reader = BufReader::new(File::open(path)?);
+        Ok(serde_json::from_reader(reader).map_err(io::Error::from)?)
     }
+}
 
-    fn sync(&mut self, data: &<Self as Agent>::Data) {
-        todo!()
+/// Turn the outputs of the network into q-values.
+/// A dueling network outputs the value of the state followed by the advantage of every action.
+pub(crate) fn q_values(outputs: &[f32], dueling: bool) -> Vec<f32> {
+    if dueling {
+        let (value, advantages) = outputs.split_first().expect("Network has no outputs");
+        let mean = advantages.iter().sum::<f32>() / advantages.len() as f32;
+        advantages.iter().map(|a| value + a - mean).collect()
+    } else {
+        outputs.to_vec()
     }
 }
 
-struct Process<'a, T, E, S, O> 
-where
-    T: Fn(&E) -> S,
-    E: Enviroment,
-{
-    buffer: &'a MemBuffer<T, E, S, Option<Target>>,
+/// A q-value target for the action taken in a state
+struct Target<'a, S> {
+    state: &'a S,
+    idx: usize,
+    target: f32,
+}
+
+struct Process<'a, S, O> {
+    samples: &'a [Target<'a, S>],
     optimizer: &'a mut O,
+    dueling: bool,
 }
 
-impl<'a, T, E, S, O> Processor for Process<'a, T, E, S, O> 
+impl<'a, S, O> Processor for Process<'a, S, O>
 where
-    T: Fn(&E) -> S,
-    E: Enviroment,
     O: Optimizer,
+    O: DerefMut,
+    O::Target: Network,
     S: AsRef<[f32]>,
 {
     fn process(&mut self, idx: usize) -> f32 {
-        if let DataPoint {state, data: Some(Target { idx, target}), .. } = &self.buffer[idx] {
-            self.optimizer.process_partial(state.as_ref(), *idx, *target)
-        }
-        else {
-            0.
+        let Target { state, idx, target } = &self.samples[idx];
+        let state = state.as_ref();
+
+        if self.dueling {
+            let outputs = self.optimizer.predict(state).as_scalar().to_vec();
+            let error = q_values(&outputs, true)[*idx] - target;
+            let n = outputs.len() - 1;
+
+            // q = v + a - mean(a), so the error is split between the value and every advantage
+            let mut loss = self.optimizer.process_partial(state, 0, outputs[0] - error);
+            for i in 0..n {
+                let grad = if i == *idx { 1. } else { 0. } - 1. / n as f32;
+                loss += self
+                    .optimizer
+                    .process_partial(state, i + 1, outputs[i + 1] - error * grad);
+            }
+            loss
+        } else {
+            self.optimizer.process_partial(state, *idx, *target)
         }
     }
 
     fn size(&self) -> usize {
-        self.buffer.len()
+        self.samples.len()
     }
 
     fn end_batch(&mut self, _batch: usize) {
@@ -152,15 +352,9 @@ where
     }
 }
 
-#[derive(Default)]
-pub struct Target {
-    idx: usize,
-    target: f32,
-}
-
 impl<E, O, T, S> Agent for QAgent<E, O, T, S>
 where
-    E: Enviroment<Action = TaggedDiscrete>,
+    E: Validate<Action = TaggedDiscrete>,
     O: Optimizer,
     O: DerefMut,
     O::Target: Network + Clone,
@@ -168,48 +362,73 @@ where
     S: AsRef<[f32]>,
 {
     type Env = E;
-    type Data = MemBuffer<T, E, S, Option<Target>>;
+    type Data = MemBuffer<T, E, S, ()>;
 
-    fn action(&mut self, env: &Self::Env, data: &Self::Data) -> <Self::Env as Enviroment>::Action {
-        let eps = (self.eps)(self.t);
-        let rand = self.rng.get_u32();
+    fn action(&mut self, env: &Self::Env, data: &Self::Data) -> <Self::Env as Validate>::Action {
+        let valid = (&self.token)
+            .into_iter()
+            .map(|action| !self.mask || env.validate(action))
+            .collect::<Vec<_>>();
+        let values = self.q_values(&data.func()(env));
 
-        if rand > (eps * u32::MAX as f32) as u32 {
-            let rewards = self.optimizer.predict(data.func()(env).as_ref());
-
-            let mut act = 0;
-            let mut max = f32::MIN;
-            for (i, f) in rewards.as_scalar().iter().enumerate() {
-                if *f > max {
-                    max = *f;
-                    act = i;
-                }
-            }
-            self.token
-                .action(act as u32)
-                .expect("Could not create action")
+        let act = if self.eval {
+            exploration::greedy(&values, &valid)
         } else {
-            self.select_random(env)
-        }
+            let mut rng = FastRng::seed(self.seeder.next_seed(), 0);
+            self.exploration.select(self.t, &values, &valid, &mut rng)
+        };
+        self.token
+            .action(act as u32)
+            .expect("Could not create action")
     }
 
     fn update(&mut self, data: &Self::Data) {
-        if data.is_full() {
-            if self.t % self.train_every == 0 {
+        if !self.eval && data.is_full() {
+            if self.t.is_multiple_of(self.train_every) {
                 self.train(data);
                 self.age += 1;
-                if self.age % self.lag == 0 {
-                    self.sync(data);
+                if self.age.is_multiple_of(self.lag) {
+                    self.sync();
                 }
             }
             self.t += 1;
         }
     }
+
+    fn set_eval(&mut self, eval: bool) {
+        self.eval = eval;
+    }
+
+    fn set_player(&mut self, player: u32) {
+        self.token.set_player(player);
+    }
+}
+
+impl<E, O, T, S> Freeze for QAgent<E, O, T, S>
+where
+    E: Validate<Action = TaggedDiscrete> + 'static,
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network + Clone + 'static,
+    T: Fn(&E) -> S + Clone + 'static,
+    S: AsRef<[f32]> + 'static,
+{
+    type Frozen = Frozen<E, O::Target, T, S>;
+
+    fn freeze(&self, data: &Self::Data) -> (Self::Frozen, <Self::Frozen as Agent>::Data) {
+        let frozen = Frozen::new(
+            (*self.optimizer).clone(),
+            self.token.clone(),
+            self.dueling,
+            self.mask,
+        );
+        (frozen, MemBuffer::new(1, data.func().clone()))
+    }
 }
 
 pub struct QBuilder<E, O, T, D>
 where
-    E: Enviroment<Action = TaggedDiscrete>,
+    E: Validate<Action = TaggedDiscrete>,
     O: Optimizer,
     O: DerefMut,
     O::Target: Network + Clone,
@@ -217,19 +436,24 @@ where
     D: AsRef<[f32]>,
 {
     optimizer: Option<O>,
-    eps: Option<Box<dyn FnMut(usize) -> f32>>,
-    q_target: Option<Box<dyn FnMut(f32, &[f32]) -> f32>>,
+    exploration: Option<Box<dyn Exploration>>,
+    q_target: Option<QTarget>,
+    learning_rate: Option<SetLearningRate<O>>,
     train_every: Option<usize>,
     lag: Option<usize>,
     config: Option<Config>,
     len: Option<usize>,
+    inputs: Option<usize>,
     func: Option<T>,
+    dueling: bool,
+    mask: bool,
+    seed: u64,
     phantom: PhantomData<*const (E, T, D)>,
 }
 
 impl<E, O, T, D> QBuilder<E, O, T, D>
 where
-    E: Enviroment<Action = TaggedDiscrete>,
+    E: Validate<Action = TaggedDiscrete>,
     O: Optimizer,
     O: DerefMut,
     O::Target: Network + Clone,
@@ -239,13 +463,18 @@ where
     pub fn new() -> Self {
         Self {
             optimizer: None,
-            eps: None,
+            exploration: None,
             q_target: None,
+            learning_rate: None,
             train_every: None,
             lag: None,
             config: None,
             len: None,
+            inputs: None,
             func: None,
+            dueling: false,
+            mask: false,
+            seed: 0,
             phantom: PhantomData,
         }
     }
@@ -254,18 +483,37 @@ where
         self.optimizer.replace(optimizer);
         self
     }
-    pub fn eps(mut self, eps: Box<dyn FnMut(usize) -> f32>) -> Self {
-        self.eps.replace(eps);
+    /// Shorthand for epsilon-greedy exploration
+    pub fn eps<P: Schedule + 'static>(mut self, eps: P) -> Self {
+        self.exploration.replace(Box::new(EpsilonGreedy::new(eps)));
+        self
+    }
+    pub fn exploration<X: Exploration + 'static>(mut self, exploration: X) -> Self {
+        self.exploration.replace(Box::new(exploration));
         self
     }
-    pub fn q_target(mut self, q_target: Box<dyn FnMut(f32, &[f32]) -> f32>) -> Self {
+    /// Computes the target of a transition from its reward and the q-values of the next state according to the target network.
+    /// Transitions which end an episode in a terminal state have the reward as their target.
+    pub fn q_target(mut self, q_target: QTarget) -> Self {
         self.q_target.replace(q_target);
         self
     }
+    /// Change the learning rate of the optimizer over the course of training, it's left alone by default
+    pub fn learning_rate<P: Schedule + 'static>(mut self, mut learning_rate: P) -> Self
+    where
+        O: LearningRate,
+    {
+        self.learning_rate
+            .replace(Box::new(move |optimizer: &mut O, t| {
+                optimizer.set_learning_rate(learning_rate.value(t))
+            }));
+        self
+    }
     pub fn train_every(mut self, train_every: usize) -> Self {
         self.train_every.replace(train_every);
         self
     }
+    /// The number of trainings between copies of the online network into the target network
     pub fn lag(mut self, lag: usize) -> Self {
         self.lag.replace(lag);
         self
@@ -282,37 +530,261 @@ where
         self.func.replace(func);
         self
     }
+    /// The size of the states made by `func`, which lets `build` check that the networks have the right number of outputs
+    pub fn inputs(mut self, inputs: usize) -> Self {
+        self.inputs.replace(inputs);
+        self
+    }
+    /// Use a dueling network, whose first output is the value of the state and the rest are the advantages of the actions
+    pub fn dueling(mut self, dueling: bool) -> Self {
+        self.dueling = dueling;
+        self
+    }
+    /// Never pick actions which the enviroment doesn't accept
+    pub fn mask(mut self, mask: bool) -> Self {
+        self.mask = mask;
+        self
+    }
+    /// Seed of the exploration, the agent derives a fresh generator from it for every action
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
 }
 
-impl<E, O, T, S> AgentBuilder<E> for QBuilder<E, O, T, S>
+impl<E, O, T, D> Default for QBuilder<E, O, T, D>
 where
-    E: Enviroment<Action = TaggedDiscrete>,
+    E: Validate<Action = TaggedDiscrete>,
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network + Clone,
+    T: Fn(&E) -> D,
+    D: AsRef<[f32]>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B, E, O, T, S> AgentBuilder<B> for QBuilder<E, O, T, S>
+where
+    E: Validate<Action = TaggedDiscrete>,
     O: Optimizer,
     O: DerefMut,
     O::Target: Network + Clone,
     T: Fn(&E) -> S,
     S: AsRef<[f32]>,
-    E: GetToken<Token = ActionToken>,
+    B: GetToken<Token = ActionToken>,
 {
-    type Data = MemBuffer<T, E, S, Option<Target>>;
+    type Data = MemBuffer<T, E, S, ()>;
     type Agent = QAgent<E, O, T, S>;
 
-    fn build(self, env: &mut E) -> (Self::Agent, Self::Data) {
-        let token = env.get_token();
+    fn build(self, env: &mut B) -> Result<(Self::Agent, Self::Data)> {
+        let mut optimizer = self.optimizer.ok_or(Error::MissingParameter("optimizer"))?;
+        let train_every = self
+            .train_every
+            .ok_or(Error::MissingParameter("train_every"))?;
+        let lag = self.lag.ok_or(Error::MissingParameter("lag"))?;
+        let config = self.config.ok_or(Error::MissingParameter("config"))?;
+        let exploration = self
+            .exploration
+            .ok_or(Error::MissingParameter("exploration"))?;
+        let q_target = self.q_target.ok_or(Error::MissingParameter("q_target"))?;
+        let len = self.len.ok_or(Error::MissingParameter("len"))?;
+        let func = self.func.ok_or(Error::MissingParameter("func"))?;
+        let inputs = self.inputs.ok_or(Error::MissingParameter("inputs"))?;
+        error::ensure(train_every > 0, "train_every", "must be at least 1")?;
+        error::ensure(lag > 0, "lag", "must be at least 1")?;
+        error::ensure(len > 0, "len", "must be at least 1")?;
+        error::ensure(
+            config.batch_size > 0,
+            "config",
+            "the batch size must be at least 1",
+        )?;
+        error::ensure(config.epochs > 0, "config", "the epochs must be at least 1")?;
+
+        // a dueling network has the value of the state before the advantages
+        let token = super::take_token(env, &mut *optimizer, inputs, self.dueling as usize)?;
         let agent = QAgent::new(
-            self.optimizer.expect("Value for 'optimizer' not provided"),
-            self.train_every
-                .expect("Value for 'train_every' not provided"),
-            self.lag.expect("Value for 'lag' not provided"),
-            self.config.expect("Value for 'config' not provided"),
+            optimizer,
+            train_every,
+            lag,
+            config,
+            self.dueling,
+            self.mask,
+            self.seed,
             token,
-            self.eps.expect("Value for 'eps' not provided"),
-            self.q_target.expect("Value for 'q_target' not provided"),
+            exploration,
+            q_target,
+            self.learning_rate,
         );
-        let data = MemBuffer::new(
-            self.len.expect("Value for 'len' not provided"),
-            self.func.expect("Value for 'func' not provided"),
+        let data = MemBuffer::new(len, func);
+        Ok((agent, data))
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::learning::exploration::Ucb;
+    use crate::enviroment::{EnvBuilder, Enviroment, IsTerminal};
+    use crate::manager::data_collector::DataCollector;
+    use crate::testing::doubles::{self, Descent, Linear, Tally, TallyBuilder};
+
+    type Func = fn(&Tally) -> Vec<f32>;
+    type Learner = QAgent<Tally, Descent, Func, Vec<f32>>;
+    type Buffer = MemBuffer<Func, Tally, Vec<f32>, ()>;
+
+    fn builder() -> QBuilder<Tally, Descent, Func, Vec<f32>> {
+        QBuilder::new()
+            .optimizer(Descent::new(Linear::new(2, 2, 0.), 0.1))
+            .exploration(Ucb::new(1.))
+            .q_target(Box::new(|reward, next: &[f32]| {
+                reward + 0.9 * next.iter().cloned().fold(f32::MIN, f32::max)
+            }))
+            .train_every(1)
+            .lag(2)
+            .config(Config {
+                batch_size: 4,
+                epochs: 1,
+            })
+            .len(8)
+            .func(doubles::state as Func)
+            .inputs(2)
+            .seed(3)
+    }
+
+    fn learner(env: &mut TallyBuilder) -> (Learner, Buffer) {
+        builder()
+            .build(env)
+            .expect("The builder has every parameter")
+    }
+
+    /// Play `steps` moves of single player episodes and return the actions
+    fn play(agent: &mut Learner, data: &mut Buffer, env: &mut Tally, steps: usize) -> Vec<u32> {
+        let mut actions = Vec::new();
+        for _ in 0..steps {
+            if env.moves().is_empty() {
+                data.begin_episode(env);
+            }
+            let action = agent.action(env, data);
+            let (status, reward) = env.step(action);
+            data.push_result(env, action, reward);
+            agent.update(data);
+            actions.push(action.action);
+            if status.is_terminal() {
+                data.end_episode(env);
+                env.reset();
+            }
+        }
+        actions
+    }
+
+    #[test]
+    fn restored_agents_continue_like_the_original() {
+        let mut builder = TallyBuilder::new(3);
+        let (mut agent, mut data) = learner(&mut builder);
+        let mut env = builder.build();
+        play(&mut agent, &mut data, &mut env, 20);
+
+        let checkpoint = agent.checkpoint(Some(&data));
+        let (mut restored, mut restored_data) = learner(&mut TallyBuilder::new(3));
+        restored
+            .restore(checkpoint, &mut restored_data)
+            .expect("The checkpoint is valid");
+        let mut restored_env = env.clone();
+
+        let expected = play(&mut agent, &mut data, &mut env, 20);
+        let found = play(&mut restored, &mut restored_data, &mut restored_env, 20);
+        assert_eq!(found, expected);
+        assert_eq!(*restored.optimizer, *agent.optimizer);
+        assert_eq!(restored.net2, agent.net2);
+    }
+
+    #[test]
+    fn invalid_checkpoints_are_rejected() {
+        let mut builder = TallyBuilder::new(3);
+        let (mut agent, mut data) = learner(&mut builder);
+        let mut checkpoint = agent.checkpoint(None);
+        checkpoint.t = 5;
+        checkpoint.lag = 0;
+        match agent.restore(checkpoint, &mut data) {
+            Err(Error::InvalidParameter { name: "lag", .. }) => (),
+            other => panic!("Expected an invalid lag, found {:?}", other.err()),
+        }
+        assert_eq!(agent.t, 0);
+    }
+
+    #[test]
+    fn dueling_values_add_the_centered_advantages_to_the_value() {
+        assert_eq!(q_values(&[1., 2., 4.], true), vec![0., 2.]);
+        assert_eq!(q_values(&[1., 2., 4.], false), vec![1., 2., 4.]);
+    }
+
+    #[test]
+    fn dueling_training_moves_the_q_value_to_its_target() {
+        let mut optimizer = Descent::new(Linear::new(2, 3, 0.), 0.1);
+        let state = vec![1., 0.];
+        let samples = [Target {
+            state: &state,
+            idx: 1,
+            target: 1.,
+        }];
+        for _ in 0..100 {
+            let processor = Process {
+                samples: &samples,
+                optimizer: &mut optimizer,
+                dueling: true,
+            };
+            Stochaistic::new(1, 1, processor).last();
+        }
+
+        let q = q_values(optimizer.predict(&state), true);
+        assert!((q[1] - 1.).abs() < 1e-4, "q-values {:?}", q);
+    }
+
+    #[test]
+    fn dueling_networks_need_an_output_for_the_value() {
+        let mut env = TallyBuilder::new(3);
+        let built = builder().dueling(true).build(&mut env);
+        match built.err() {
+            Some(Error::Dimensions {
+                expected: 3,
+                found: 2,
+            }) => (),
+            other => panic!("Expected the value output to be missing, found {:?}", other),
+        }
+        // the failed build gave its token back
+        assert_eq!(env.get_token().player(), 0);
+    }
+
+    #[cfg(feature = "record")]
+    #[test]
+    fn checkpoints_can_be_saved() {
+        let mut builder = TallyBuilder::new(3);
+        let (mut agent, mut data) = learner(&mut builder);
+        let mut env = builder.build();
+        play(&mut agent, &mut data, &mut env, 10);
+
+        let path = std::env::temp_dir().join("reinforced_q_checkpoint.json");
+        let checkpoint = agent.checkpoint(Some(&data));
+        checkpoint
+            .save(&path)
+            .expect("The checkpoint should be written");
+        let loaded = QCheckpoint::<Linear, Vec<f32>>::load(&path);
+        std::fs::remove_file(&path).expect("The checkpoint should be removed");
+        let loaded = loaded.expect("The checkpoint should be readable");
+
+        assert_eq!(loaded.network, checkpoint.network);
+        assert_eq!(loaded.t, checkpoint.t);
+        assert_eq!(loaded.exploration, checkpoint.exploration);
+        assert_eq!(
+            loaded.buffer.map(|buffer| buffer.data.len()),
+            Some(data.len())
         );
-        (agent, data)
     }
 }
@@ -0,0 +1,384 @@
+use std::ops::DerefMut;
+
+use random_fast_rng::FastRng;
+
+use super::policy::{self, Mask, Targets, Trajectory};
+use crate::agent::{frozen::Frozen, Agent, AgentBuilder, Freeze};
+use crate::enviroment::{
+    discrete::{ActionToken, TaggedDiscrete},
+    GetToken, Validate,
+};
+use crate::error::{self, Error, Result};
+use crate::manager::data_collector::mem_buffer::MemBuffer;
+use crate::misc::random;
+
+use rusty_nn::helpers::AsScalarExt;
+use rusty_nn::network::Network;
+use rusty_nn::optimizer::Optimizer;
+use rusty_nn::trainer::{Config, Stochaistic};
+
+use std::marker::PhantomData;
+
+/// Monte Carlo policy gradient agent.
+/// Actions are sampled from a softmax over the outputs of the policy network,
+/// which is trained at the end of every episode on the discounted returns.
+/// The optional baseline network has a single output which learns to predict the return.
+pub struct Reinforce<E, O, T, S>
+where
+    E: Validate<Action = TaggedDiscrete>,
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network,
+    T: Fn(&E) -> S,
+{
+    policy: O,
+    baseline: Option<O>,
+    token: ActionToken,
+
+    gamma: f32,
+    config: Config,
+    mask: bool,
+    eval: bool,
+
+    rng: FastRng,
+
+    phantom: PhantomData<*const (E, T, S)>,
+}
+
+impl<E, O, T, S> Reinforce<E, O, T, S>
+where
+    E: Validate<Action = TaggedDiscrete>,
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network,
+    T: Fn(&E) -> S,
+    S: AsRef<[f32]>,
+{
+    fn new(
+        policy: O,
+        baseline: Option<O>,
+        gamma: f32,
+        config: Config,
+        mask: bool,
+        seed: u64,
+        token: ActionToken,
+    ) -> Self {
+        Self {
+            policy,
+            baseline,
+            token,
+            gamma,
+            config,
+            mask,
+            eval: false,
+            rng: FastRng::seed(seed, 0),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<E, O, T, S> Agent for Reinforce<E, O, T, S>
+where
+    E: Validate<Action = TaggedDiscrete>,
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network,
+    T: Fn(&E) -> S,
+    S: AsRef<[f32]>,
+{
+    type Env = E;
+    type Data = MemBuffer<T, E, S, Mask>;
+
+    fn action(&mut self, env: &Self::Env, data: &Self::Data) -> <Self::Env as Validate>::Action {
+        let mask = if self.mask {
+            Some(policy::valid_actions(&self.token, env))
+        } else {
+            None
+        };
+
+        let scores = self.policy.predict(data.func()(env).as_ref());
+        let probs = policy::softmax(scores.as_scalar(), mask.as_deref());
+        let act = if self.eval {
+            policy::argmax(&probs)
+        } else {
+            random::sample(&mut self.rng, &probs)
+        };
+        if let (false, Some(mask), Some(latest)) = (self.eval, mask, data.back()) {
+            latest.data().set(mask);
+        }
+        self.token
+            .action(act as u32)
+            .expect("Could not create action")
+    }
+
+    fn update(&mut self, _data: &Self::Data) {}
+
+    fn end_episode(&mut self, data: &Self::Data) {
+        if self.eval {
+            return;
+        }
+        let trajectory = match data.episodes().last() {
+            Some(episode) => Trajectory::new(episode),
+            None => return,
+        };
+        if trajectory.actions.is_empty() {
+            return;
+        }
+
+        // pair every action with the state in which it was taken
+        let steps = trajectory
+            .states
+            .iter()
+            .zip(trajectory.actions)
+            .map(|(state, action)| (*state, action))
+            .collect::<Vec<_>>();
+        let returns = policy::discounted_returns(&trajectory.rewards, self.gamma);
+
+        let advantages = match &mut self.baseline {
+            Some(baseline) => {
+                let advantages = steps
+                    .iter()
+                    .zip(&returns)
+                    .map(|((state, _), ret)| ret - baseline.predict(state.as_ref()).as_scalar()[0])
+                    .collect::<Vec<_>>();
+
+                let samples = steps
+                    .iter()
+                    .zip(&returns)
+                    .map(|((state, _), ret)| (*state, vec![*ret]))
+                    .collect::<Vec<_>>();
+                let processor = Targets {
+                    samples: &samples,
+                    optimizer: baseline,
+                };
+                Stochaistic::new(self.config.batch_size, self.config.epochs, processor).last();
+
+                advantages
+            }
+            None => returns,
+        };
+
+        let mut samples = Vec::with_capacity(steps.len());
+        let masks = trajectory.masks.iter();
+        for (((state, action), advantage), mask) in steps.iter().zip(&advantages).zip(masks) {
+            let scores = self.policy.predict(state.as_ref()).as_scalar().to_vec();
+            let probs = policy::softmax(&scores, mask.as_deref());
+            let grad = policy::log_prob_gradient(&probs, *action, *advantage);
+            let targets = scores.iter().zip(grad).map(|(s, g)| s + g).collect();
+            samples.push((*state, targets));
+        }
+
+        let processor = Targets {
+            samples: &samples,
+            optimizer: &mut self.policy,
+        };
+        Stochaistic::new(self.config.batch_size, self.config.epochs, processor).last();
+    }
+
+    fn set_eval(&mut self, eval: bool) {
+        self.eval = eval;
+    }
+
+    fn set_player(&mut self, player: u32) {
+        self.token.set_player(player);
+    }
+}
+
+impl<E, O, T, S> Freeze for Reinforce<E, O, T, S>
+where
+    E: Validate<Action = TaggedDiscrete> + 'static,
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network + Clone + 'static,
+    T: Fn(&E) -> S + Clone + 'static,
+    S: AsRef<[f32]> + 'static,
+{
+    type Frozen = Frozen<E, O::Target, T, S>;
+
+    fn freeze(&self, data: &Self::Data) -> (Self::Frozen, <Self::Frozen as Agent>::Data) {
+        let frozen = Frozen::new((*self.policy).clone(), self.token.clone(), false, self.mask);
+        (frozen, MemBuffer::new(1, data.func().clone()))
+    }
+}
+
+pub struct ReinforceBuilder<E, O, T, S>
+where
+    E: Validate<Action = TaggedDiscrete>,
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network,
+    T: Fn(&E) -> S,
+    S: AsRef<[f32]>,
+{
+    policy: Option<O>,
+    baseline: Option<O>,
+    gamma: Option<f32>,
+    config: Option<Config>,
+    mask: bool,
+    seed: u64,
+    len: Option<usize>,
+    inputs: Option<usize>,
+    func: Option<T>,
+    phantom: PhantomData<*const (E, T, S)>,
+}
+
+impl<E, O, T, S> ReinforceBuilder<E, O, T, S>
+where
+    E: Validate<Action = TaggedDiscrete>,
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network,
+    T: Fn(&E) -> S,
+    S: AsRef<[f32]>,
+{
+    pub fn new() -> Self {
+        Self {
+            policy: None,
+            baseline: None,
+            gamma: None,
+            config: None,
+            mask: false,
+            seed: 0,
+            len: None,
+            inputs: None,
+            func: None,
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn policy(mut self, policy: O) -> Self {
+        self.policy.replace(policy);
+        self
+    }
+    /// A network with a single output used to reduce the variance of the updates
+    pub fn baseline(mut self, baseline: O) -> Self {
+        self.baseline.replace(baseline);
+        self
+    }
+    pub fn gamma(mut self, gamma: f32) -> Self {
+        self.gamma.replace(gamma);
+        self
+    }
+    pub fn config(mut self, config: Config) -> Self {
+        self.config.replace(config);
+        self
+    }
+    /// Give actions which the enviroment doesn't accept zero probability, both when sampling and in the policy gradient
+    pub fn mask(mut self, mask: bool) -> Self {
+        self.mask = mask;
+        self
+    }
+    /// Seed of the random generator which samples actions from the policy
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+    /// The length of the buffer, which has to fit a whole episode
+    pub fn len(mut self, len: usize) -> Self {
+        self.len.replace(len);
+        self
+    }
+    pub fn func(mut self, func: T) -> Self {
+        self.func.replace(func);
+        self
+    }
+    /// The size of the states made by `func`, which lets `build` check that the networks have the right number of outputs
+    pub fn inputs(mut self, inputs: usize) -> Self {
+        self.inputs.replace(inputs);
+        self
+    }
+}
+
+impl<E, O, T, S> Default for ReinforceBuilder<E, O, T, S>
+where
+    E: Validate<Action = TaggedDiscrete>,
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network,
+    T: Fn(&E) -> S,
+    S: AsRef<[f32]>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B, E, O, T, S> AgentBuilder<B> for ReinforceBuilder<E, O, T, S>
+where
+    E: Validate<Action = TaggedDiscrete>,
+    O: Optimizer,
+    O: DerefMut,
+    O::Target: Network,
+    T: Fn(&E) -> S,
+    S: AsRef<[f32]>,
+    B: GetToken<Token = ActionToken>,
+{
+    type Data = MemBuffer<T, E, S, Mask>;
+    type Agent = Reinforce<E, O, T, S>;
+
+    fn build(self, env: &mut B) -> Result<(Self::Agent, Self::Data)> {
+        let mut policy = self.policy.ok_or(Error::MissingParameter("policy"))?;
+        let gamma = self.gamma.ok_or(Error::MissingParameter("gamma"))?;
+        let config = self.config.ok_or(Error::MissingParameter("config"))?;
+        let len = self.len.ok_or(Error::MissingParameter("len"))?;
+        let func = self.func.ok_or(Error::MissingParameter("func"))?;
+        let inputs = self.inputs.ok_or(Error::MissingParameter("inputs"))?;
+        error::ensure(
+            (0. ..=1.).contains(&gamma),
+            "gamma",
+            "must be between 0 and 1",
+        )?;
+        error::ensure(len > 0, "len", "must be at least 1")?;
+        error::ensure(
+            config.batch_size > 0,
+            "config",
+            "the batch size must be at least 1",
+        )?;
+
+        let mut baseline = self.baseline;
+        if let Some(baseline) = &mut baseline {
+            super::check_outputs(&mut **baseline, inputs, 1)?;
+        }
+        let token = super::take_token(env, &mut *policy, inputs, 0)?;
+        let agent = Reinforce::new(policy, baseline, gamma, config, self.mask, self.seed, token);
+        let data = MemBuffer::new(len, func);
+        Ok((agent, data))
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enviroment::EnvBuilder;
+    use crate::manager::runner::Runner;
+    use crate::testing::doubles::{self, Descent, Linear, Tally, TallyBuilder};
+
+    type Func = fn(&Tally) -> Vec<f32>;
+
+    #[test]
+    fn rewarded_actions_become_likely() {
+        let builder = ReinforceBuilder::new()
+            .policy(Descent::new(Linear::new(2, 2, 0.), 0.1))
+            .gamma(1.)
+            .config(Config {
+                batch_size: 1,
+                epochs: 1,
+            })
+            .len(8)
+            .func(doubles::state as Func)
+            .inputs(2);
+        let mut runner = Runner::new(builder, TallyBuilder::new(2).build())
+            .expect("The builder has every parameter");
+
+        // both actions score the same at first, so the evaluation picks the first one
+        assert_eq!(runner.evaluate(1).expect("One episode is enough"), 0.);
+        for _ in 0..100 {
+            runner.episode();
+        }
+        assert_eq!(runner.evaluate(1).expect("One episode is enough"), 2.);
+    }
+}
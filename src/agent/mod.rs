@@ -1,21 +1,45 @@
-use crate::enviroment::Enviroment;
+use crate::enviroment::Validate;
+use crate::error::Result;
 use crate::manager::data_collector::DataCollector;
 
 // pub mod adapter;
+pub mod frozen;
 pub mod learning;
+pub mod recording;
 
 /// This trait is used to querry agents for an action.
 pub trait Agent {
-    type Env: Enviroment;
+    type Env: Validate;
     type Data: DataCollector<Env = Self::Env>;
 
-    fn action(&mut self, env: &Self::Env, data: &Self::Data) -> <Self::Env as Enviroment>::Action;
+    fn action(&mut self, env: &Self::Env, data: &Self::Data) -> <Self::Env as Validate>::Action;
     fn update(&mut self, data: &Self::Data);
+
+    /// Called after the final update of an episode
+    fn end_episode(&mut self, _data: &Self::Data) {}
+
+    /// In evaluation mode agents act deterministically and don't learn
+    fn set_eval(&mut self, _eval: bool) {}
+
+    /// Move the agent to the seat of `player`, so its actions are tagged with that player.
+    /// Used to seat agents which weren't built for their seat, like snapshots and tournament entrants.
+    fn set_player(&mut self, _player: u32) {}
+}
+
+/// Agents which can make frozen copies of themselves, like self-play opponents
+pub trait Freeze: Agent {
+    type Frozen: Agent<Env = Self::Env> + 'static;
+
+    fn freeze(&self, data: &Self::Data) -> (Self::Frozen, <Self::Frozen as Agent>::Data);
 }
 
 pub trait AgentBuilder<E> {
     type Data: DataCollector;
     type Agent: Agent<Data = Self::Data>;
 
-    fn build(self, env: &mut E) -> (Self::Agent, Self::Data);
+    /// Fails if a parameter is missing or invalid, in which case no player is taken from `env`
+    fn build(self, env: &mut E) -> Result<(Self::Agent, Self::Data)>;
+
+    /// Seed the random generator of the agent, agents which don't use randomness can ignore it
+    fn set_seed(&mut self, _seed: u64) {}
 }
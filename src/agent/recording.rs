@@ -0,0 +1,107 @@
+use crate::agent::{Agent, AgentBuilder};
+use crate::enviroment::Validate;
+use crate::error::Result;
+use crate::manager::data_collector::DataCollector;
+
+use std::marker::PhantomData;
+
+/// An agent which also feeds its experience to the collector `C`, like a `Recorder`.
+/// It acts and learns only from its own data.
+pub struct Recording<A, C> {
+    agent: A,
+    phantom: PhantomData<*const C>,
+}
+
+impl<A, C> Agent for Recording<A, C>
+where
+    A: Agent,
+    C: DataCollector<Env = A::Env>,
+    <A::Env as Validate>::Action: Clone,
+{
+    type Env = A::Env;
+    type Data = (A::Data, C);
+
+    fn action(&mut self, env: &Self::Env, data: &Self::Data) -> <Self::Env as Validate>::Action {
+        self.agent.action(env, &data.0)
+    }
+
+    fn update(&mut self, data: &Self::Data) {
+        self.agent.update(&data.0)
+    }
+
+    fn end_episode(&mut self, data: &Self::Data) {
+        self.agent.end_episode(&data.0)
+    }
+
+    fn set_eval(&mut self, eval: bool) {
+        self.agent.set_eval(eval)
+    }
+
+    fn set_player(&mut self, player: u32) {
+        self.agent.set_player(player)
+    }
+}
+
+/// Builds the agent of `builder` and records its experience with `collector`
+pub struct RecordingBuilder<B, C> {
+    builder: B,
+    collector: C,
+}
+
+impl<B, C> RecordingBuilder<B, C> {
+    pub fn new(builder: B, collector: C) -> Self {
+        Self { builder, collector }
+    }
+}
+
+impl<E, B, C, V> AgentBuilder<E> for RecordingBuilder<B, C>
+where
+    B: AgentBuilder<E>,
+    B::Agent: Agent<Env = V>,
+    B::Data: DataCollector<Env = V>,
+    C: DataCollector<Env = V>,
+    V: Validate,
+    V::Action: Clone,
+{
+    type Data = (B::Data, C);
+    type Agent = Recording<B::Agent, C>;
+
+    fn build(self, env: &mut E) -> Result<(Self::Agent, Self::Data)> {
+        let (agent, data) = self.builder.build(env)?;
+        let agent = Recording {
+            agent,
+            phantom: PhantomData,
+        };
+        Ok((agent, (data, self.collector)))
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.builder.set_seed(seed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manager::ManagerBuilder;
+    use crate::testing::doubles::{Log, ScriptBuilder, TallyBuilder};
+
+    #[test]
+    fn both_collectors_see_every_episode() {
+        let (own, recorded) = (Log::default(), Log::default());
+        let script = ScriptBuilder {
+            actions: vec![1, 0],
+            log: own.clone(),
+        };
+        let mut builder = ManagerBuilder::new(TallyBuilder::new(3));
+        builder
+            .add_agent(RecordingBuilder::new(script, recorded.clone()))
+            .expect("A single agent fits the enviroment");
+        let mut manager = builder.build().expect("A single agent fits the enviroment");
+        manager.episode();
+        manager.episode();
+
+        assert_eq!(own.entries().len(), 10);
+        assert_eq!(recorded.entries(), own.entries());
+    }
+}
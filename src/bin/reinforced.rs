@@ -0,0 +1,30 @@
+//! Runs an experiment file, see `reinforced::experiment` for its format.
+//! Usage: `reinforced <experiment.toml>`.
+
+use reinforced::experiment::{self, Dense, Experiment};
+use reinforced::Error;
+
+use std::env;
+use std::fs;
+use std::io;
+use std::process;
+
+fn main() {
+    let args = env::args().collect::<Vec<_>>();
+    if args.len() != 2 {
+        eprintln!("Usage: {} <experiment.toml>", args[0]);
+        process::exit(2);
+    }
+
+    let res = fs::read_to_string(&args[1])
+        .map_err(Error::from)
+        .and_then(|toml| {
+            let experiment = Experiment::from_toml(&toml)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            experiment::run(&experiment, &mut Dense)
+        });
+    if let Err(err) = res {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,48 @@
+//! Steps through a game written by `Game::write`, showing the board after every move.
+//! Usage: `replay <file> [game]`, where `game` is the index of the line in the file.
+//! Only tic-tac-toe games can be replayed for now.
+
+use reinforced::enviroment::discrete::TaggedDiscrete;
+use reinforced::games::TicTacToe;
+use reinforced::manager::data_collector::recorder::{Game, GameReader};
+use reinforced::manager::replay;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::process;
+
+fn main() {
+    let args = env::args().collect::<Vec<_>>();
+    if args.len() < 2 || args.len() > 3 {
+        eprintln!("Usage: {} <file> [game]", args[0]);
+        process::exit(2);
+    }
+    let index = match args.get(2).map(|arg| arg.parse::<usize>()) {
+        Some(Ok(index)) => index,
+        Some(Err(_)) => fail("The game index has to be a number"),
+        None => 0,
+    };
+
+    let file = File::open(&args[1]).unwrap_or_else(|err| fail(&err.to_string()));
+    let game: Game<TaggedDiscrete> = match GameReader::new(BufReader::new(file)).nth(index) {
+        Some(Ok(game)) => game,
+        Some(Err(err)) => fail(&err.to_string()),
+        None => fail(&format!("The file doesn't contain game {}", index)),
+    };
+
+    let stdin = io::stdin();
+    if let Err(err) = replay::replay(
+        &mut TicTacToe::new(),
+        &game.actions,
+        stdin.lock(),
+        io::stdout(),
+    ) {
+        fail(&err.to_string());
+    }
+}
+
+fn fail(msg: &str) -> ! {
+    eprintln!("{}", msg);
+    process::exit(1);
+}
@@ -0,0 +1,33 @@
+//! Runs a hyperparameter sweep file, see `reinforced::experiment::sweep` for its format.
+//! Usage: `sweep <sweep.toml>`.
+
+use reinforced::experiment::{
+    sweep::{self, Sweep},
+    Dense,
+};
+use reinforced::Error;
+
+use std::env;
+use std::fs;
+use std::io;
+use std::process;
+
+fn main() {
+    let args = env::args().collect::<Vec<_>>();
+    if args.len() != 2 {
+        eprintln!("Usage: {} <sweep.toml>", args[0]);
+        process::exit(2);
+    }
+
+    let res = fs::read_to_string(&args[1])
+        .map_err(Error::from)
+        .and_then(|toml| {
+            let sweep = Sweep::from_toml(&toml)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            sweep::run(&sweep, Dense)
+        });
+    if let Err(err) = res {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
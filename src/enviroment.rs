@@ -1,22 +1,76 @@
-pub trait Enviroment {
+/// What agents and data collectors see of an enviroment, which only has to tell whether an action is allowed.
+/// Agents pick their actions through it, so they never reset or step what they're shown.
+pub trait Validate {
     type Action;
+
+    fn validate(&self, action: Self::Action) -> bool;
+}
+
+pub trait Enviroment: Validate {
     type Status;
 
     fn reset(&mut self);
     fn step(&mut self, action: Self::Action) -> (Self::Status, f32);
-    fn validate(&self, action: Self::Action) -> bool;
+}
+
+/// Enviroments in which all players act at the same time, like rock-paper-scissors or auctions.
+/// Agents still see the enviroment through `Validate`, but the manager only calls `step_joint`.
+pub trait Simultaneous: Enviroment {
+    /// Apply the actions of all agents, indexed like the agents,
+    /// and return the new status together with the reward of every agent.
+    fn step_joint(&mut self, actions: Vec<Self::Action>) -> (Self::Status, Vec<f32>);
+}
+
+/// Enviroments with hidden information, which show each player only the part of the state it's allowed to see
+pub trait Observe: Enviroment {
+    /// What a single player sees, it only has to validate actions like the enviroment
+    type Observation: Validate<Action = Self::Action>;
+
+    /// Returns the observation of `player`, which is the player tagged in its actions
+    fn observe(&self, player: u32) -> Self::Observation;
+}
+
+/// An observation in place of the enviroment, which is what agents and data collectors of observers get
+#[derive(Clone, Debug)]
+pub struct Observed<O>(pub O);
+
+impl<O: Validate> Validate for Observed<O> {
+    type Action = O::Action;
+
+    fn validate(&self, action: Self::Action) -> bool {
+        self.0.validate(action)
+    }
+}
+
+impl<O> std::ops::Deref for Observed<O> {
+    type Target = O;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Enviroments which can be shown as text, for debugging and replays
+pub trait Render {
+    fn render(&self) -> String;
 }
 
 pub trait EnvBuilder {
     type Output: Enviroment;
 
     fn build(self) -> Self::Output;
+
+    /// Seed the randomness of the enviroment, deterministic enviroments can ignore it
+    fn set_seed(&mut self, _seed: u64) {}
 }
 
 pub trait GetToken {
     type Token;
 
     fn get_token(&mut self) -> Self::Token;
+
+    /// Take back the last token handed out, for agents which fail to build after taking one
+    fn return_token(&mut self, token: Self::Token);
 }
 
 pub trait PlayerRange {
@@ -34,8 +88,28 @@ pub trait SingleWinner {
     const LOSS: f32;
 }
 
+/// Decides the rewards agents receive once the enviroment reaches a terminal state,
+/// which lets enviroments express draws, cooperative games or games with several winners.
+pub trait AssignRewards: Enviroment {
+    /// Returns the reward of every agent, which is added to the rewards they received from `step`.
+    /// `last` is the index of the agent which made the final move and `players` is the number of agents.
+    fn final_rewards(&self, status: &Self::Status, last: usize, players: usize) -> Vec<f32>;
+}
+
+impl<E: Enviroment + SingleWinner> AssignRewards for E {
+    fn final_rewards(&self, _status: &Self::Status, last: usize, players: usize) -> Vec<f32> {
+        (0..players)
+            .map(|i| if i == last { 0. } else { E::LOSS })
+            .collect()
+    }
+}
+
 pub mod discrete {
-    #[derive(Copy, Clone)]
+    #[cfg(feature = "serde")]
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Copy, Clone, Debug)]
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
     pub struct TaggedDiscrete {
         pub action: u32,
         pub player: u32,
@@ -48,8 +122,29 @@ pub mod discrete {
     }
 
     impl ActionToken {
+        /// Create a token for `player` which can take actions in the range `0..=max`
+        pub fn new(player: u32, max: u32) -> Self {
+            Self { player, max }
+        }
+
+        pub fn player(&self) -> u32 {
+            self.player
+        }
+
+        /// Tag the actions of the token with another player
+        pub fn set_player(&mut self, player: u32) {
+            self.player = player;
+        }
+
+        /// Returns the number of available actions
+        // a token always has at least one action, so it can't be empty
+        #[allow(clippy::len_without_is_empty)]
+        pub fn len(&self) -> usize {
+            self.max as usize + 1
+        }
+
         pub fn action(&self, action: u32) -> Option<TaggedDiscrete> {
-            if action > self.max {
+            if action <= self.max {
                 Some(TaggedDiscrete {
                     action,
                     player: self.player,
@@ -92,3 +187,39 @@ pub mod discrete {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Ends on any action, whoever takes it wins
+    struct Race;
+
+    impl Validate for Race {
+        type Action = ();
+
+        fn validate(&self, _action: Self::Action) -> bool {
+            true
+        }
+    }
+
+    impl Enviroment for Race {
+        type Status = bool;
+
+        fn reset(&mut self) {}
+
+        fn step(&mut self, _action: Self::Action) -> (Self::Status, f32) {
+            (true, 0.)
+        }
+    }
+
+    impl SingleWinner for Race {
+        const LOSS: f32 = -2.;
+    }
+
+    #[test]
+    fn single_winners_leave_everyone_else_with_the_loss() {
+        assert_eq!(Race.final_rewards(&true, 1, 3), vec![-2., 0., -2.]);
+        assert_eq!(Race.final_rewards(&true, 0, 1), vec![0.]);
+    }
+}
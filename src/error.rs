@@ -0,0 +1,112 @@
+use std::fmt;
+use std::io;
+
+/// Errors of building agents, managers and experiments
+#[derive(Debug)]
+pub enum Error {
+    /// A builder is missing a value which has no default
+    MissingParameter(&'static str),
+    /// A parameter has a value the agent can't work with
+    InvalidParameter {
+        name: &'static str,
+        reason: &'static str,
+    },
+    /// The enviroment doesn't accept this number of agents
+    PlayerCount {
+        count: usize,
+        min: usize,
+        max: Option<usize>,
+    },
+    /// A snapshot of a buffer doesn't describe a valid buffer, usually because it's corrupt
+    InvalidSnapshot(&'static str),
+    /// A network doesn't have as many outputs as the agent needs
+    Dimensions {
+        expected: usize,
+        found: usize,
+    },
+    /// A replayed action isn't valid in the state the enviroment is in
+    InvalidAction(usize),
+    /// There is no agent with this index
+    AgentIndex {
+        index: usize,
+        count: usize,
+    },
+    /// An agent was paired with itself
+    SelfMatch(usize),
+    /// An agent is missing from a tournament, because a match it played in didn't finish
+    MissingAgent(usize),
+    /// An agent doesn't have a TrueSkill rating
+    MissingRating(usize),
+    Io(io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::MissingParameter(name) => write!(f, "Value for '{}' not provided", name),
+            Error::InvalidParameter { name, reason } => {
+                write!(f, "Invalid value for '{}': {}", name, reason)
+            }
+            Error::PlayerCount { count, min, max } => match max {
+                Some(max) => write!(
+                    f,
+                    "The enviroment needs {} to {} agents, but {} were added",
+                    min, max, count
+                ),
+                None => write!(
+                    f,
+                    "The enviroment needs at least {} agents, but {} were added",
+                    min, count
+                ),
+            },
+            Error::InvalidSnapshot(reason) => write!(f, "Invalid snapshot: {}", reason),
+            Error::Dimensions { expected, found } => write!(
+                f,
+                "The network has {} outputs, but the agent needs {}",
+                found, expected
+            ),
+            Error::InvalidAction(index) => {
+                write!(f, "Action {} of the replay isn't valid", index)
+            }
+            Error::AgentIndex { index, count } => {
+                write!(f, "There is no agent {}, there are only {}", index, count)
+            }
+            Error::SelfMatch(index) => write!(f, "Agent {} can't play against itself", index),
+            Error::MissingAgent(index) => write!(
+                f,
+                "Agent {} is missing, a match it played in didn't finish",
+                index
+            ),
+            Error::MissingRating(index) => {
+                write!(f, "Agent {} doesn't have a TrueSkill rating", index)
+            }
+            Error::Io(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+/// Fail with `Error::InvalidParameter` unless `valid` holds
+pub(crate) fn ensure(valid: bool, name: &'static str, reason: &'static str) -> Result<()> {
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::InvalidParameter { name, reason })
+    }
+}
@@ -0,0 +1,506 @@
+//! Experiments described by TOML files, which the `reinforced` binary runs.
+//!
+//! ```toml
+//! name = "q-vs-q"
+//! episodes = 10000
+//! seed = 42
+//!
+//! [enviroment]
+//! type = "tic-tac-toe"
+//!
+//! [[agents]]
+//! type = "q"
+//! train_every = 4
+//! lag = 100
+//! len = 1000
+//! batch_size = 32
+//! epochs = 1
+//! gamma = 0.99
+//! eps = { type = "linear", start = 1.0, end = 0.05, steps = 5000 }
+//! network = { hidden = [64, 64], learning_rate = 0.001 }
+//! learning_rate = { type = "exponential", start = 0.001, rate = 0.9999, min = 0.0001 }
+//!
+//! [log]
+//! every = 100
+//! sinks = [{ type = "stdout" }, { type = "csv", path = "q-vs-q.csv" }]
+//! ```
+
+pub mod sweep;
+
+use crate::agent::learning::{q_learn::QBuilder, LearningRate};
+use crate::enviroment::{
+    discrete::{ActionToken, TaggedDiscrete},
+    AssignRewards, EnvBuilder, Enviroment, GetToken, IsTerminal, PlayerRange,
+};
+use crate::error::{self, Error, Result};
+use crate::games::tic_tac_toe::{TicTacToe, TicTacToeBuilder};
+use crate::manager::{ManagerBuilder, Outcome};
+use crate::misc::schedule::{Constant, Exponential, Linear, Schedule};
+
+use rusty_nn::functions::Activation;
+use rusty_nn::network::{FeedForward, LinearBuilder, Network};
+use rusty_nn::optimizer::{GradientDescent, Optimizer};
+use rusty_nn::trainer::Config;
+
+use serde::{Deserialize, Serialize};
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::ops::DerefMut;
+use std::path::PathBuf;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Experiment {
+    pub name: String,
+    pub enviroment: EnvConfig,
+    pub episodes: usize,
+    /// The master seed of the enviroment, the agents and the turn order
+    #[serde(default)]
+    pub seed: u64,
+    pub agents: Vec<AgentConfig>,
+    #[serde(default)]
+    pub log: LogConfig,
+}
+
+impl Experiment {
+    pub fn from_toml(toml: &str) -> std::result::Result<Self, toml::de::Error> {
+        toml::from_str(toml)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum EnvConfig {
+    TicTacToe,
+    /// Any other enviroment, which `run` rejects
+    #[serde(other)]
+    Unsupported,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum AgentConfig {
+    Q(QConfig),
+    /// Any other agent, which `run` rejects
+    #[serde(other)]
+    Unsupported,
+}
+
+/// The hyperparameters of a `QBuilder`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct QConfig {
+    pub train_every: usize,
+    pub lag: usize,
+    /// The length of the replay buffer
+    pub len: usize,
+    pub batch_size: usize,
+    pub epochs: usize,
+    /// The discount of the q-learning target
+    pub gamma: f32,
+    pub eps: ScheduleConfig,
+    /// Changes the learning rate of the network during training, which otherwise keeps the one of `network`
+    #[serde(default)]
+    pub learning_rate: Option<ScheduleConfig>,
+    #[serde(default)]
+    pub dueling: bool,
+    /// Never pick actions which the enviroment doesn't accept, required by enviroments which panic on invalid actions
+    #[serde(default = "default_mask")]
+    pub mask: bool,
+    pub network: NetworkSpec,
+}
+
+fn default_mask() -> bool {
+    true
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum ScheduleConfig {
+    Constant { value: f32 },
+    Linear { start: f32, end: f32, steps: usize },
+    Exponential { start: f32, rate: f32, min: f32 },
+}
+
+impl ScheduleConfig {
+    fn schedule(self) -> impl FnMut(usize) -> f32 {
+        let mut schedule: Box<dyn Schedule> = match self {
+            ScheduleConfig::Constant { value } => Box::new(Constant(value)),
+            ScheduleConfig::Linear { start, end, steps } => Box::new(Linear { start, end, steps }),
+            ScheduleConfig::Exponential { start, rate, min } => {
+                Box::new(Exponential { start, rate, min })
+            }
+        };
+        move |t| schedule.value(t)
+    }
+}
+
+/// Describes a network for a `NetworkFactory`, the inputs and outputs are decided by the enviroment and agent
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct NetworkSpec {
+    /// The sizes of the hidden layers
+    pub hidden: Vec<usize>,
+    pub learning_rate: f32,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LogConfig {
+    /// The number of episodes summarized by a single row
+    pub every: usize,
+    pub sinks: Vec<Sink>,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            every: 100,
+            sinks: vec![Sink::Stdout],
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Sink {
+    Stdout,
+    Csv {
+        path: PathBuf,
+    },
+    /// A json object on every line
+    Json {
+        path: PathBuf,
+    },
+}
+
+/// Builds the networks of agents, which keeps the construction of networks out of the experiment runner
+/// Their optimizers have to expose the learning rate, so that `QConfig::learning_rate` can schedule it
+pub trait NetworkFactory {
+    type Network: Network + Clone + 'static;
+    type Optimizer: Optimizer + DerefMut<Target = Self::Network> + LearningRate + 'static;
+
+    fn build(
+        &mut self,
+        spec: &NetworkSpec,
+        inputs: usize,
+        outputs: usize,
+        seed: u64,
+    ) -> Self::Optimizer;
+}
+
+/// Fully connected networks with relu hidden layers and a linear output layer,
+/// trained by gradient descent with the learning rate of their `NetworkSpec`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Dense;
+
+impl NetworkFactory for Dense {
+    type Network = FeedForward;
+    type Optimizer = GradientDescent<FeedForward>;
+
+    fn build(
+        &mut self,
+        spec: &NetworkSpec,
+        inputs: usize,
+        outputs: usize,
+        seed: u64,
+    ) -> Self::Optimizer {
+        let network = spec
+            .hidden
+            .iter()
+            .fold(LinearBuilder::new(inputs), |builder, size| {
+                builder.dense(*size, Activation::Relu)
+            })
+            .dense(outputs, Activation::Identity)
+            .build(seed);
+        GradientDescent::new(network, spec.learning_rate)
+    }
+}
+
+/// Statistics of the agents over `LogConfig::every` episodes
+#[derive(Clone, Debug, Serialize)]
+pub struct Row {
+    /// The number of episodes played when the row was logged
+    pub episode: usize,
+    pub win_rate: Vec<f32>,
+    pub mean_reward: Vec<f32>,
+}
+
+/// Train the agents of the experiment and return the logged rows
+/// Fails with `Error::InvalidParameter` for enviroments and agents other than tic-tac-toe and q-learning
+pub fn run<F: NetworkFactory>(experiment: &Experiment, factory: &mut F) -> Result<Vec<Row>> {
+    match experiment.enviroment {
+        EnvConfig::TicTacToe => {
+            // the board panics when a taken cell is played
+            for agent in experiment.agents.iter() {
+                if let AgentConfig::Q(config) = agent {
+                    error::ensure(
+                        config.mask,
+                        "mask",
+                        "tic-tac-toe only accepts free cells, so the agents have to mask their actions",
+                    )?;
+                }
+            }
+            train(
+                experiment,
+                TicTacToeBuilder::new(),
+                encode_tic_tac_toe,
+                18,
+                9,
+                factory,
+            )
+        }
+        EnvConfig::Unsupported => Err(Error::InvalidParameter {
+            name: "enviroment",
+            reason: "only tic-tac-toe is supported",
+        }),
+    }
+}
+
+/// Each cell is encoded as whether it belongs to the first and to the second player
+fn encode_tic_tac_toe(env: &TicTacToe) -> Vec<f32> {
+    env.board()
+        .iter()
+        .flat_map(|cell| {
+            vec![
+                (*cell == Some(0)) as u8 as f32,
+                (*cell == Some(1)) as u8 as f32,
+            ]
+        })
+        .collect()
+}
+
+fn train<B, F>(
+    experiment: &Experiment,
+    env: B,
+    encode: fn(&B::Output) -> Vec<f32>,
+    inputs: usize,
+    actions: usize,
+    factory: &mut F,
+) -> Result<Vec<Row>>
+where
+    B: EnvBuilder + GetToken<Token = ActionToken> + PlayerRange,
+    B::Output: Enviroment<Action = TaggedDiscrete> + AssignRewards + 'static,
+    <B::Output as Enviroment>::Status: IsTerminal,
+    F: NetworkFactory,
+{
+    let mut builder = ManagerBuilder::new(env);
+    builder.seed(experiment.seed)?;
+    let mut seeder = builder.fork_seeder().expect("The manager was seeded above");
+    for agent in experiment.agents.iter() {
+        match agent {
+            AgentConfig::Q(config) => {
+                let outputs = actions + config.dueling as usize;
+                let optimizer = factory.build(&config.network, inputs, outputs, seeder.next_seed());
+                let gamma = config.gamma;
+                let q = QBuilder::new()
+                    .optimizer(optimizer)
+                    .train_every(config.train_every)
+                    .lag(config.lag)
+                    .len(config.len)
+                    .config(Config {
+                        batch_size: config.batch_size,
+                        epochs: config.epochs,
+                    })
+                    .q_target(Box::new(move |reward, next: &[f32]| {
+                        reward + gamma * next.iter().cloned().fold(f32::MIN, f32::max)
+                    }))
+                    .dueling(config.dueling)
+                    .mask(config.mask)
+                    .func(encode)
+                    .inputs(inputs);
+                let q = q.eps(config.eps.schedule());
+                let q = match config.learning_rate {
+                    Some(learning_rate) => q.learning_rate(learning_rate.schedule()),
+                    None => q,
+                };
+                builder.add_agent(q)?;
+            }
+            AgentConfig::Unsupported => {
+                return Err(Error::InvalidParameter {
+                    name: "agents",
+                    reason: "only q-learning agents are supported",
+                })
+            }
+        }
+    }
+
+    let mut manager = builder.build()?;
+    let mut logger = Logger::new(&experiment.log, experiment.agents.len())?;
+    let every = experiment.log.every.max(1);
+    let mut wins = vec![0usize; experiment.agents.len()];
+    let mut rewards = vec![0f32; experiment.agents.len()];
+    for episode in 1..=experiment.episodes {
+        let summary = manager.episode();
+        for agent in 0..wins.len() {
+            if summary.outcome(agent) == Outcome::Win {
+                wins[agent] += 1;
+            }
+            rewards[agent] += summary.rewards[agent];
+        }
+
+        if episode % every == 0 || episode == experiment.episodes {
+            let played = (episode - 1) % every + 1;
+            logger.log(Row {
+                episode,
+                win_rate: wins.iter().map(|w| *w as f32 / played as f32).collect(),
+                mean_reward: rewards.iter().map(|r| r / played as f32).collect(),
+            })?;
+            wins.iter_mut().for_each(|w| *w = 0);
+            rewards.iter_mut().for_each(|r| *r = 0.);
+        }
+    }
+    Ok(logger.finish()?)
+}
+
+struct Logger {
+    sinks: Vec<(Sink, Box<dyn Write>)>,
+    rows: Vec<Row>,
+}
+
+impl Logger {
+    fn new(config: &LogConfig, agents: usize) -> io::Result<Self> {
+        let mut sinks = Vec::new();
+        for sink in config.sinks.iter() {
+            let mut writer: Box<dyn Write> = match sink {
+                Sink::Stdout => Box::new(io::stdout()),
+                Sink::Csv { path } | Sink::Json { path } => {
+                    Box::new(BufWriter::new(File::create(path)?))
+                }
+            };
+            if let Sink::Csv { .. } = sink {
+                write!(writer, "episode")?;
+                for agent in 0..agents {
+                    write!(writer, ",win_rate_{0},mean_reward_{0}", agent)?;
+                }
+                writeln!(writer)?;
+            }
+            sinks.push((sink.clone(), writer));
+        }
+        Ok(Self {
+            sinks,
+            rows: Vec::new(),
+        })
+    }
+
+    fn log(&mut self, row: Row) -> io::Result<()> {
+        for (sink, writer) in self.sinks.iter_mut() {
+            match sink {
+                Sink::Stdout => {
+                    write!(writer, "episode {}:", row.episode)?;
+                    for (agent, (win, reward)) in
+                        row.win_rate.iter().zip(&row.mean_reward).enumerate()
+                    {
+                        write!(
+                            writer,
+                            " agent {} wins {:.3} reward {:.3};",
+                            agent, win, reward
+                        )?;
+                    }
+                    writeln!(writer)?;
+                }
+                Sink::Csv { .. } => {
+                    write!(writer, "{}", row.episode)?;
+                    for (win, reward) in row.win_rate.iter().zip(&row.mean_reward) {
+                        write!(writer, ",{},{}", win, reward)?;
+                    }
+                    writeln!(writer)?;
+                }
+                Sink::Json { .. } => {
+                    serde_json::to_writer(&mut *writer, &row).map_err(io::Error::from)?;
+                    writeln!(writer)?;
+                }
+            }
+        }
+        self.rows.push(row);
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<Vec<Row>> {
+        for (_, writer) in self.sinks.iter_mut() {
+            writer.flush()?;
+        }
+        Ok(self.rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::doubles::{Descent, Linear as LinearNet};
+
+    /// Networks which start out predicting zeros
+    struct Zeros;
+
+    impl NetworkFactory for Zeros {
+        type Network = LinearNet;
+        type Optimizer = Descent;
+
+        fn build(
+            &mut self,
+            spec: &NetworkSpec,
+            inputs: usize,
+            outputs: usize,
+            _seed: u64,
+        ) -> Descent {
+            Descent::new(LinearNet::new(inputs, outputs, 0.), spec.learning_rate)
+        }
+    }
+
+    const AGENT: &str = r#"
+        type = "q"
+        train_every = 1
+        lag = 4
+        len = 8
+        batch_size = 4
+        epochs = 1
+        gamma = 0.9
+        eps = { type = "linear", start = 1.0, end = 0.0, steps = 10 }
+        network = { hidden = [], learning_rate = 0.01 }
+    "#;
+
+    fn experiment(enviroment: &str, agent: &str) -> Experiment {
+        let toml = format!(
+            "name = \"test\"\nepisodes = 6\nseed = 3\n\n[enviroment]\ntype = \"{}\"\n\n[[agents]]\n{}\n[[agents]]\n{}\n[log]\nevery = 4\nsinks = []\n",
+            enviroment, AGENT, agent
+        );
+        Experiment::from_toml(&toml).expect("A valid experiment")
+    }
+
+    #[test]
+    fn experiments_log_a_row_every_few_episodes() {
+        let rows = run(&experiment("tic-tac-toe", AGENT), &mut Zeros).expect("A valid experiment");
+        let episodes = rows.iter().map(|row| row.episode).collect::<Vec<_>>();
+        assert_eq!(episodes, vec![4, 6]);
+        assert_eq!(rows[0].win_rate.len(), 2);
+    }
+
+    #[test]
+    fn unsupported_enviroments_and_agents_are_rejected() {
+        match run(&experiment("cart-pole", AGENT), &mut Zeros) {
+            Err(Error::InvalidParameter {
+                name: "enviroment", ..
+            }) => (),
+            _ => panic!("Expected the enviroment to be rejected"),
+        }
+        match run(&experiment("tic-tac-toe", "type = \"ppo\""), &mut Zeros) {
+            Err(Error::InvalidParameter { name: "agents", .. }) => (),
+            _ => panic!("Expected the agent to be rejected"),
+        }
+    }
+
+    #[test]
+    fn schedules_follow_their_config() {
+        let mut linear = ScheduleConfig::Linear {
+            start: 1.,
+            end: 0.,
+            steps: 4,
+        }
+        .schedule();
+        assert_eq!((linear(0), linear(2), linear(8)), (1., 0.5, 0.));
+        let mut exponential = ScheduleConfig::Exponential {
+            start: 1.,
+            rate: 0.5,
+            min: 0.2,
+        }
+        .schedule();
+        assert_eq!((exponential(1), exponential(4)), (0.5, 0.2));
+    }
+}
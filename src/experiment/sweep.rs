@@ -0,0 +1,315 @@
+//! Grid and random search over the hyperparameters of the q-learning agents of an experiment.
+//!
+//! ```toml
+//! threads = 4
+//! results = "sweep.csv"
+//! search = { type = "random", trials = 20, seed = 1 }
+//!
+//! [parameters]
+//! train_every = [1, 4, 8]
+//! lag = [50, 200]
+//! eps = [
+//!     { type = "constant", value = 0.1 },
+//!     { type = "linear", start = 1.0, end = 0.05, steps = 5000 },
+//! ]
+//!
+//! [experiment]
+//! # an experiment like the ones run by `experiment::run`
+//! ```
+
+use super::{AgentConfig, Experiment, NetworkFactory, QConfig, Row, ScheduleConfig};
+use crate::error::Result;
+use crate::misc::random;
+
+use random_fast_rng::FastRng;
+use serde::{Deserialize, Serialize};
+
+use std::any::Any;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Sweep {
+    pub experiment: Experiment,
+    pub search: Search,
+    pub parameters: Parameters,
+    /// The indices of the tuned agents, all of them by default
+    #[serde(default)]
+    pub agents: Option<Vec<usize>>,
+    /// The number of trials which run at the same time
+    #[serde(default = "default_threads")]
+    pub threads: usize,
+    /// Where to write the table of results as csv
+    pub results: PathBuf,
+}
+
+fn default_threads() -> usize {
+    1
+}
+
+impl Sweep {
+    pub fn from_toml(toml: &str) -> std::result::Result<Self, toml::de::Error> {
+        toml::from_str(toml)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Search {
+    /// Try every combination of the parameters
+    Grid,
+    /// Try `trials` combinations with every value picked uniformly
+    Random { trials: usize, seed: u64 },
+}
+
+/// The values to try for each hyperparameter. Empty lists keep the value from the experiment.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Parameters {
+    pub train_every: Vec<usize>,
+    pub lag: Vec<usize>,
+    pub len: Vec<usize>,
+    pub batch_size: Vec<usize>,
+    pub epochs: Vec<usize>,
+    pub gamma: Vec<f32>,
+    pub learning_rate: Vec<f32>,
+    pub eps: Vec<ScheduleConfig>,
+}
+
+const DIMENSIONS: usize = 8;
+
+impl Parameters {
+    fn sizes(&self) -> [usize; DIMENSIONS] {
+        [
+            self.train_every.len(),
+            self.lag.len(),
+            self.len.len(),
+            self.batch_size.len(),
+            self.epochs.len(),
+            self.gamma.len(),
+            self.learning_rate.len(),
+            self.eps.len(),
+        ]
+    }
+
+    fn apply(&self, choice: &[usize; DIMENSIONS], config: &mut QConfig) {
+        fn set<T: Clone>(values: &[T], idx: usize, field: &mut T) {
+            if let Some(value) = values.get(idx) {
+                *field = value.clone();
+            }
+        }
+        set(&self.train_every, choice[0], &mut config.train_every);
+        set(&self.lag, choice[1], &mut config.lag);
+        set(&self.len, choice[2], &mut config.len);
+        set(&self.batch_size, choice[3], &mut config.batch_size);
+        set(&self.epochs, choice[4], &mut config.epochs);
+        set(&self.gamma, choice[5], &mut config.gamma);
+        set(
+            &self.learning_rate,
+            choice[6],
+            &mut config.network.learning_rate,
+        );
+        set(&self.eps, choice[7], &mut config.eps);
+    }
+
+    /// Returns the names and values of the parameters which are searched over
+    fn describe(&self, choice: &[usize; DIMENSIONS]) -> Vec<(&'static str, String)> {
+        fn push<T: std::fmt::Debug>(
+            out: &mut Vec<(&'static str, String)>,
+            name: &'static str,
+            values: &[T],
+            idx: usize,
+        ) {
+            if let Some(value) = values.get(idx) {
+                out.push((name, format!("{:?}", value)));
+            }
+        }
+        let mut out = Vec::new();
+        push(&mut out, "train_every", &self.train_every, choice[0]);
+        push(&mut out, "lag", &self.lag, choice[1]);
+        push(&mut out, "len", &self.len, choice[2]);
+        push(&mut out, "batch_size", &self.batch_size, choice[3]);
+        push(&mut out, "epochs", &self.epochs, choice[4]);
+        push(&mut out, "gamma", &self.gamma, choice[5]);
+        push(&mut out, "learning_rate", &self.learning_rate, choice[6]);
+        push(&mut out, "eps", &self.eps, choice[7]);
+        out
+    }
+}
+
+/// The parameters of a single trial and the last logged row of its experiment
+#[derive(Clone, Debug)]
+pub struct Trial {
+    pub parameters: Vec<(&'static str, String)>,
+    pub result: Option<Row>,
+    /// Why the trial failed, if it returned an error or panicked
+    pub error: Option<String>,
+}
+
+/// Run every trial of the sweep and write the table of results.
+/// Trials which fail or panic don't stop the others, their error is kept in their row instead.
+pub fn run<F>(sweep: &Sweep, factory: F) -> Result<Vec<Trial>>
+where
+    F: NetworkFactory + Clone + Send + 'static,
+{
+    let choices = choices(sweep);
+    let experiments = choices
+        .iter()
+        .map(|choice| trial_experiment(sweep, choice))
+        .collect::<Vec<_>>();
+    let queue = Arc::new(Mutex::new(experiments.into_iter().enumerate()));
+
+    let (sender, receiver) = mpsc::channel();
+    let workers = (0..sweep.threads.max(1))
+        .map(|_| {
+            let queue = Arc::clone(&queue);
+            let sender = sender.clone();
+            let factory = factory.clone();
+            thread::spawn(move || loop {
+                let next = queue
+                    .lock()
+                    .expect("The queue of trials is poisoned")
+                    .next();
+                match next {
+                    Some((i, experiment)) => {
+                        // a panicking trial could leave the factory broken, so every trial gets its own
+                        let mut factory = factory.clone();
+                        let rows = panic::catch_unwind(AssertUnwindSafe(|| {
+                            super::run(&experiment, &mut factory)
+                        }));
+                        let rows = match rows {
+                            Ok(rows) => rows.map_err(|err| err.to_string()),
+                            Err(payload) => Err(panic_message(payload)),
+                        };
+                        if sender.send((i, rows)).is_err() {
+                            break;
+                        }
+                    }
+                    None => break,
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    drop(sender);
+
+    let mut results = receiver.into_iter().collect::<Vec<_>>();
+    for worker in workers {
+        worker.join().expect("A trial panicked");
+    }
+    results.sort_by_key(|(i, _)| *i);
+
+    let mut trials = Vec::with_capacity(results.len());
+    for ((_, rows), choice) in results.into_iter().zip(&choices) {
+        let (result, error) = match rows {
+            Ok(mut rows) => (rows.pop(), None),
+            Err(err) => (None, Some(err)),
+        };
+        trials.push(Trial {
+            parameters: sweep.parameters.describe(choice),
+            result,
+            error,
+        });
+    }
+    write_results(sweep, &trials)?;
+    Ok(trials)
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(msg) => format!("panicked: {}", msg),
+        Err(payload) => match payload.downcast::<&'static str>() {
+            Ok(msg) => format!("panicked: {}", msg),
+            Err(_) => "panicked".to_string(),
+        },
+    }
+}
+
+fn choices(sweep: &Sweep) -> Vec<[usize; DIMENSIONS]> {
+    let sizes = sweep.parameters.sizes();
+    match sweep.search {
+        Search::Grid => {
+            let count = sizes.iter().map(|size| (*size).max(1)).product::<usize>();
+            (0..count)
+                .map(|mut n| {
+                    let mut choice = [0; DIMENSIONS];
+                    for (c, size) in choice.iter_mut().zip(sizes.iter()) {
+                        let size = (*size).max(1);
+                        *c = n % size;
+                        n /= size;
+                    }
+                    choice
+                })
+                .collect()
+        }
+        Search::Random { trials, seed } => {
+            let mut rng = FastRng::seed(seed, 0);
+            (0..trials)
+                .map(|_| {
+                    let mut choice = [0; DIMENSIONS];
+                    for (c, size) in choice.iter_mut().zip(sizes.iter()) {
+                        *c = random::below(&mut rng, (*size).max(1));
+                    }
+                    choice
+                })
+                .collect()
+        }
+    }
+}
+
+/// The experiment of a single trial, which doesn't log anywhere so trials don't overwrite each other's files
+fn trial_experiment(sweep: &Sweep, choice: &[usize; DIMENSIONS]) -> Experiment {
+    let mut experiment = sweep.experiment.clone();
+    experiment.log.sinks.clear();
+    for (i, agent) in experiment.agents.iter_mut().enumerate() {
+        let tuned = sweep
+            .agents
+            .as_ref()
+            .is_none_or(|agents| agents.contains(&i));
+        if tuned {
+            match agent {
+                AgentConfig::Q(config) => sweep.parameters.apply(choice, config),
+                // the trial fails once `experiment::run` rejects it
+                AgentConfig::Unsupported => (),
+            }
+        }
+    }
+    experiment
+}
+
+fn write_results(sweep: &Sweep, trials: &[Trial]) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(&sweep.results)?);
+    let names = sweep.parameters.describe(&[0; DIMENSIONS]);
+    write!(writer, "trial")?;
+    for (name, _) in names.iter() {
+        write!(writer, ",{}", name)?;
+    }
+    let agents = sweep.experiment.agents.len();
+    for agent in 0..agents {
+        write!(writer, ",win_rate_{0},mean_reward_{0}", agent)?;
+    }
+    writeln!(writer, ",error")?;
+
+    for (i, trial) in trials.iter().enumerate() {
+        write!(writer, "{}", i)?;
+        for (_, value) in trial.parameters.iter() {
+            write!(writer, ",\"{}\"", value.replace('"', "\"\""))?;
+        }
+        match &trial.result {
+            Some(row) => {
+                for (win, reward) in row.win_rate.iter().zip(&row.mean_reward) {
+                    write!(writer, ",{},{}", win, reward)?;
+                }
+            }
+            None => write!(writer, "{}", ",,".repeat(agents))?,
+        }
+        match &trial.error {
+            Some(error) => writeln!(writer, ",\"{}\"", error.replace('"', "\"\""))?,
+            None => writeln!(writer, ",")?,
+        }
+    }
+    writer.flush()
+}
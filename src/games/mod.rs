@@ -0,0 +1,3 @@
+pub mod tic_tac_toe;
+
+pub use tic_tac_toe::TicTacToe;
@@ -0,0 +1,221 @@
+use crate::enviroment::{
+    discrete::{ActionToken, TaggedDiscrete},
+    AssignRewards, EnvBuilder, Enviroment, GetToken, IsTerminal, PlayerRange, Render, Validate,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Status {
+    Running,
+    /// The player which completed a line
+    Won(u32),
+    Draw,
+}
+
+impl IsTerminal for Status {
+    fn is_terminal(&self) -> bool {
+        *self != Status::Running
+    }
+}
+
+/// Tic-tac-toe for two players. Actions are the indices of the cells, row by row.
+/// Whoever moves first plays crosses, so the game works with any turn order.
+/// The winner gets the reward of 1 and the loser -1 at the end of the game.
+#[derive(Clone, Debug)]
+pub struct TicTacToe {
+    /// The player which owns each cell
+    board: [Option<u32>; 9],
+    first: Option<u32>,
+    last: Option<u32>,
+    status: Status,
+}
+
+const LINES: [[usize; 3]; 8] = [
+    [0, 1, 2],
+    [3, 4, 5],
+    [6, 7, 8],
+    [0, 3, 6],
+    [1, 4, 7],
+    [2, 5, 8],
+    [0, 4, 8],
+    [2, 4, 6],
+];
+
+impl TicTacToe {
+    pub fn new() -> Self {
+        Self {
+            board: [None; 9],
+            first: None,
+            last: None,
+            status: Status::Running,
+        }
+    }
+
+    pub fn status(&self) -> Status {
+        self.status
+    }
+
+    /// Returns the player which owns each cell
+    pub fn board(&self) -> &[Option<u32>; 9] {
+        &self.board
+    }
+}
+
+impl Default for TicTacToe {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Validate for TicTacToe {
+    type Action = TaggedDiscrete;
+
+    fn validate(&self, action: Self::Action) -> bool {
+        self.status == Status::Running
+            && action.player < 2
+            && self.last != Some(action.player)
+            && self
+                .board
+                .get(action.action as usize)
+                .is_some_and(Option::is_none)
+    }
+}
+
+impl Enviroment for TicTacToe {
+    type Status = Status;
+
+    fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    fn step(&mut self, action: Self::Action) -> (Self::Status, f32) {
+        assert!(self.validate(action), "Invalid action {:?}", action);
+        self.board[action.action as usize] = Some(action.player);
+        self.first.get_or_insert(action.player);
+        self.last.replace(action.player);
+
+        let won = LINES
+            .iter()
+            .any(|line| line.iter().all(|&i| self.board[i] == Some(action.player)));
+        self.status = if won {
+            Status::Won(action.player)
+        } else if self.board.iter().all(Option::is_some) {
+            Status::Draw
+        } else {
+            Status::Running
+        };
+        (self.status, 0.)
+    }
+}
+
+impl AssignRewards for TicTacToe {
+    fn final_rewards(&self, status: &Self::Status, _last: usize, players: usize) -> Vec<f32> {
+        (0..players)
+            .map(|i| match status {
+                Status::Won(winner) if *winner as usize == i => 1.,
+                Status::Won(_) => -1.,
+                _ => 0.,
+            })
+            .collect()
+    }
+}
+
+impl Render for TicTacToe {
+    fn render(&self) -> String {
+        let mut out = String::new();
+        for (row, cells) in self.board.chunks(3).enumerate() {
+            if row > 0 {
+                out.push_str("-+-+-\n");
+            }
+            let cells = cells
+                .iter()
+                .map(|cell| match cell {
+                    Some(player) if Some(*player) == self.first => "X",
+                    Some(_) => "O",
+                    None => " ",
+                })
+                .collect::<Vec<_>>();
+            out.push_str(&cells.join("|"));
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Hands out the tokens of the two players in order
+#[derive(Clone, Debug, Default)]
+pub struct TicTacToeBuilder {
+    players: u32,
+}
+
+impl TicTacToeBuilder {
+    pub fn new() -> Self {
+        Self { players: 0 }
+    }
+}
+
+impl EnvBuilder for TicTacToeBuilder {
+    type Output = TicTacToe;
+
+    fn build(self) -> Self::Output {
+        TicTacToe::new()
+    }
+}
+
+impl GetToken for TicTacToeBuilder {
+    type Token = ActionToken;
+
+    fn get_token(&mut self) -> Self::Token {
+        assert!(self.players < 2, "Tic-tac-toe is played by two players");
+        self.players += 1;
+        ActionToken::new(self.players - 1, 8)
+    }
+
+    fn return_token(&mut self, _token: Self::Token) {
+        self.players -= 1;
+    }
+}
+
+impl PlayerRange for TicTacToeBuilder {
+    const MIN: usize = 2;
+    const MAX: Option<usize> = Some(2);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(cells: &[u32]) -> (TicTacToe, Status) {
+        let mut game = TicTacToe::new();
+        let mut status = Status::Running;
+        for (i, cell) in cells.iter().enumerate() {
+            let action = TaggedDiscrete {
+                action: *cell,
+                player: (i % 2) as u32,
+            };
+            status = game.step(action).0;
+        }
+        (game, status)
+    }
+
+    #[test]
+    fn the_winner_gets_one_and_the_loser_minus_one() {
+        let (game, status) = play(&[4, 0, 3, 1, 5]);
+        assert_eq!(status, Status::Won(0));
+        assert_eq!(game.final_rewards(&status, 0, 2), vec![1., -1.]);
+
+        let (game, status) = play(&[4, 0, 8, 1, 3, 2]);
+        assert_eq!(status, Status::Won(1));
+        assert_eq!(game.final_rewards(&status, 1, 2), vec![-1., 1.]);
+    }
+
+    #[test]
+    fn draws_reward_nobody() {
+        let (game, status) = play(&[0, 4, 8, 1, 7, 6, 2, 5, 3]);
+        assert_eq!(status, Status::Draw);
+        assert_eq!(game.final_rewards(&status, 0, 2), vec![0., 0.]);
+    }
+}
@@ -1,4 +1,11 @@
 pub mod agent;
 pub mod enviroment;
+pub mod error;
+#[cfg(feature = "cli")]
+pub mod experiment;
+pub mod games;
 pub mod manager;
 pub mod misc;
+pub mod testing;
+
+pub use error::{Error, Result};
@@ -1,7 +1,11 @@
-use crate::enviroment::Enviroment;
+use crate::enviroment::Validate;
+use crate::error::{Error, Result};
 
 use super::{DataCollector, DataPoint, Transition};
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 use std::{marker::PhantomData, ops::{Deref, DerefMut}};
 use std::{
     collections::vec_deque::{self, VecDeque},
@@ -11,24 +15,67 @@ use std::{
 pub struct MemBuffer<F, E, S, D>
 where
     F: Fn(&E) -> S,
-    E: Enviroment,
+    E: Validate,
 {
     buffer: VecDeque<DataPoint<S, E::Action, D>>,
     func: F,
     head: usize,
+    /// Whether each episode in the buffer has reached a terminal state
+    terminal: VecDeque<bool>,
     marker: PhantomData<*const E>,
 }
 
+/// The contents of a `MemBuffer` without its state function
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Snapshot<S, A, D> {
+    pub capacity: usize,
+    pub head: usize,
+    /// Whether each episode has reached a terminal state
+    pub terminal: Vec<bool>,
+    pub data: Vec<DataPoint<S, A, D>>,
+}
+
+impl<S, A, D> Snapshot<S, A, D> {
+    /// Check that the data forms a sequence of whole episodes, that `head` points to the first node of one of them
+    /// and that there is a terminal flag for every episode
+    pub fn is_valid(&self) -> bool {
+        let mut idx = 0;
+        let mut head_found = self.data.is_empty() && self.head == 0;
+        let mut episodes = 0;
+        while idx < self.data.len() {
+            let len = match self.data[idx].transition {
+                Transition::First { len } if len > 0 => len,
+                _ => return false,
+            };
+            if idx + len > self.data.len() {
+                return false;
+            }
+            let trans = self.data[idx + 1..idx + len]
+                .iter()
+                .all(|data_point| matches!(data_point.transition, Transition::Trans { .. }));
+            if !trans {
+                return false;
+            }
+            head_found |= idx == self.head;
+            idx += len;
+            episodes += 1;
+        }
+        head_found && episodes == self.terminal.len()
+    }
+}
+
 impl<F, E, S, D> MemBuffer<F, E, S, D>
 where
     F: Fn(&E) -> S,
-    E: Enviroment,
+    E: Validate,
 {
     pub fn new(size: usize, func: F) -> Self {
         Self {
             buffer: VecDeque::with_capacity(size),
             func,
             head: 0,
+            terminal: VecDeque::new(),
             marker: PhantomData,
         }
     }
@@ -41,20 +88,61 @@ where
         &self.func
     }
 
+    /// Returns whether the `episode`th episode in the buffer has reached a terminal state.
+    /// Episodes which are still running or were truncated are not terminal.
+    pub fn is_terminal(&self, episode: usize) -> bool {
+        self.terminal.get(episode).copied().unwrap_or(false)
+    }
+
     pub fn episodes<'a>(&'a self) -> Episodes<'a, S, E::Action, D> {
-        Episodes {
-            buffer: &self.buffer,
-            head: 0,
-        }
+        Episodes::new(&self.buffer)
     }
 
     pub fn episodes_mut<'a>(&'a mut self) -> EpisodesMut<'a, S, E::Action, D> {
-        EpisodesMut {
-            buffer: &mut self.buffer,
-            head: 0,
+        EpisodesMut::new(&mut self.buffer)
+    }
+
+    /// Copy the contents of the buffer
+    pub fn snapshot(&self) -> Snapshot<S, E::Action, D>
+    where
+        S: Clone,
+        E::Action: Clone,
+        D: Clone,
+    {
+        Snapshot {
+            capacity: self.buffer.capacity(),
+            head: self.head,
+            terminal: self.terminal.iter().copied().collect(),
+            data: self.buffer.iter().cloned().collect(),
         }
     }
 
+    /// Create a buffer from a snapshot
+    pub fn from_snapshot(snapshot: Snapshot<S, E::Action, D>, func: F) -> Result<Self> {
+        let mut buffer = Self::new(snapshot.capacity, func);
+        buffer.restore(snapshot)?;
+        Ok(buffer)
+    }
+
+    /// Replace the contents of the buffer with a snapshot.
+    /// Snapshots usually come from disk, so a corrupt one is reported as an error and leaves the buffer untouched.
+    pub fn restore(&mut self, snapshot: Snapshot<S, E::Action, D>) -> Result<()> {
+        if snapshot.data.len() > snapshot.capacity {
+            return Err(Error::InvalidSnapshot(
+                "the snapshot contains more data than its capacity",
+            ));
+        }
+        if !snapshot.is_valid() {
+            return Err(Error::InvalidSnapshot("the timeline of the snapshot is broken"));
+        }
+        let mut buffer = VecDeque::with_capacity(snapshot.capacity);
+        buffer.extend(snapshot.data);
+        self.buffer = buffer;
+        self.head = snapshot.head;
+        self.terminal = snapshot.terminal.into_iter().collect();
+        Ok(())
+    }
+
     // pub fn iter<'a>(&'a self) -> vec_deque::Iter<'a, DataPoint<S, E::Action, D>> {
     //     self.buffer.iter()
     // }
@@ -79,8 +167,14 @@ where
                         .buffer
                         .front_mut()
                         .expect("Call me when the buffer isn't empty");
-                    if let Transition::Trans { .. } = first.transition {
-                        first.transition = Transition::First { len: len - 1 }
+                    match first.transition {
+                        Transition::Trans { .. } => {
+                            first.transition = Transition::First { len: len - 1 }
+                        }
+                        // the whole episode has been dropped
+                        Transition::First { .. } => {
+                            self.terminal.pop_front();
+                        }
                     }
                 }
                 Transition::Trans { .. } => {
@@ -90,17 +184,17 @@ where
         }
     }
 
-    fn push_data(&mut self, data: DataPoint<S, <E as Enviroment>::Action, D>) {
-        let old = None;
+    fn push_data(&mut self, data: DataPoint<S, <E as Validate>::Action, D>) {
+        let mut old = None;
         if self.buffer.len() == self.buffer.capacity() {
             old = self.buffer.pop_front();
-            self.head.saturating_sub(1);
+            self.head = self.head.saturating_sub(1);
         }
         self.truncate_data(old);
         self.buffer.push_back(data);
         match &mut self.buffer[self.head].transition {
             Transition::First { len } => *len += 1,
-            Transition::Trans { action, reward } => {
+            Transition::Trans { .. } => {
                 panic!("Head does not point to first data node")
             }
         }
@@ -110,7 +204,7 @@ where
 impl<F, E, S, D> DataCollector for MemBuffer<F, E, S, D>
 where
     F: Fn(&E) -> S,
-    E: Enviroment,
+    E: Validate,
     D: Default
 {
     type Env = E;
@@ -123,13 +217,14 @@ where
         };
         // the index of the first data node will be the current buffer length
         self.head = self.buffer.len();
+        self.terminal.push_back(false);
         self.push_data(data_point);
     }
 
     fn push_result(
         &mut self,
         env: &Self::Env,
-        action: <Self::Env as Enviroment>::Action,
+        action: <Self::Env as Validate>::Action,
         reward: f32,
     ) {
         let data_point = DataPoint {
@@ -140,12 +235,18 @@ where
 
         self.push_data(data_point);
     }
+
+    fn end_episode(&mut self, _env: &Self::Env) {
+        if let Some(terminal) = self.terminal.back_mut() {
+            *terminal = true;
+        }
+    }
 }
 
 impl<F, E, S, D> Deref for MemBuffer<F, E, S, D>
 where
     F: Fn(&E) -> S,
-    E: Enviroment,
+    E: Validate,
 {
     type Target = VecDeque<DataPoint<S, E::Action, D>>;
 
@@ -157,7 +258,7 @@ where
 impl<F, E, S, D> DerefMut for MemBuffer<F, E, S, D>
 where
     F: Fn(&E) -> S,
-    E: Enviroment,
+    E: Validate,
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.buffer
@@ -171,6 +272,12 @@ mod episodes {
         buffer: &'a VecDeque<DataPoint<S, A, D>>,
         head: usize,
     }
+
+    impl<'a, S, A, D> Episodes<'a, S, A, D> {
+        pub(crate) fn new(buffer: &'a VecDeque<DataPoint<S, A, D>>) -> Self {
+            Self { buffer, head: 0 }
+        }
+    }
     
     impl<'a, S, A, D> Iterator for Episodes<'a, S, A, D> {
         type Item = Episode<'a, S, A, D>;
@@ -184,7 +291,7 @@ mod episodes {
                 let head = self.head;
                 self.head += len;
                 Some(Episode {
-                    iter: self.buffer.range(head..self.head).into_iter(),
+                    iter: self.buffer.range(head..self.head),
                 })
             } else {
                 None
@@ -243,6 +350,12 @@ mod episodes_mut {
         buffer: &'a mut VecDeque<DataPoint<S, A, D>>,
         head: usize,
     }
+
+    impl<'a, S, A, D> EpisodesMut<'a, S, A, D> {
+        pub(crate) fn new(buffer: &'a mut VecDeque<DataPoint<S, A, D>>) -> Self {
+            Self { buffer, head: 0 }
+        }
+    }
     
     impl<'a, S, A, D> Iterator for EpisodesMut<'a, S, A, D> {
         type Item = EpisodeMut<'a, S, A, D>;
@@ -256,7 +369,12 @@ mod episodes_mut {
                 let head = self.head;
                 self.head += len;
                 Some(EpisodeMut {
-                    iter: unsafe { std::mem::transmute(self.buffer.range_mut(head..self.head).into_iter()) },
+                    iter: unsafe {
+                        std::mem::transmute::<
+                            vec_deque::IterMut<'_, DataPoint<S, A, D>>,
+                            vec_deque::IterMut<'a, DataPoint<S, A, D>>,
+                        >(self.buffer.range_mut(head..self.head))
+                    },
                 })
             } else {
                 None
@@ -364,3 +482,68 @@ mod episodes_mut {
 //         }
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn first(len: usize) -> DataPoint<u32, u32, ()> {
+        DataPoint {
+            state: 0,
+            transition: Transition::First { len },
+            data: (),
+        }
+    }
+
+    fn trans() -> DataPoint<u32, u32, ()> {
+        DataPoint {
+            state: 0,
+            transition: Transition::Trans {
+                action: 0,
+                reward: 0.,
+            },
+            data: (),
+        }
+    }
+
+    fn snapshot(
+        head: usize,
+        terminal: usize,
+        data: Vec<DataPoint<u32, u32, ()>>,
+    ) -> Snapshot<u32, u32, ()> {
+        Snapshot {
+            capacity: 10,
+            head,
+            terminal: vec![true; terminal],
+            data,
+        }
+    }
+
+    #[test]
+    fn whole_episodes_are_valid() {
+        assert!(snapshot(0, 0, Vec::new()).is_valid());
+        assert!(snapshot(0, 2, vec![first(2), trans(), first(1)]).is_valid());
+        assert!(snapshot(2, 2, vec![first(2), trans(), first(1)]).is_valid());
+    }
+
+    #[test]
+    fn head_has_to_start_an_episode() {
+        assert!(!snapshot(1, 2, vec![first(2), trans(), first(1)]).is_valid());
+        assert!(!snapshot(3, 2, vec![first(2), trans(), first(1)]).is_valid());
+        assert!(!snapshot(1, 0, Vec::new()).is_valid());
+    }
+
+    #[test]
+    fn episodes_have_to_be_whole() {
+        assert!(!snapshot(0, 1, vec![first(3), trans()]).is_valid());
+        assert!(!snapshot(0, 1, vec![trans(), trans()]).is_valid());
+        assert!(!snapshot(0, 2, vec![first(2), first(1)]).is_valid());
+        assert!(!snapshot(0, 1, vec![first(0)]).is_valid());
+    }
+
+    #[test]
+    fn every_episode_needs_a_terminal_flag() {
+        assert!(!snapshot(0, 1, vec![first(2), trans(), first(1)]).is_valid());
+        assert!(!snapshot(0, 3, vec![first(2), trans(), first(1)]).is_valid());
+    }
+}
@@ -1,21 +1,61 @@
 pub mod mem_buffer;
+#[cfg(feature = "record")]
+pub mod recorder;
+pub mod rollout;
 
-use crate::enviroment::Enviroment;
+use crate::enviroment::Validate;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 pub trait DataCollector {
-    type Env: Enviroment;
+    type Env: Validate;
 
     fn begin_episode(&mut self, env: &Self::Env);
 
     fn push_result(
         &mut self,
         env: &Self::Env,
-        action: <Self::Env as Enviroment>::Action,
+        action: <Self::Env as Validate>::Action,
         reward: f32,
     );
+
+    /// Called once the enviroment reaches a terminal state
+    fn end_episode(&mut self, _env: &Self::Env) {}
+}
+
+/// Feeds the same episodes to both collectors, like the buffer of an agent and a `Recorder`
+impl<A, B> DataCollector for (A, B)
+where
+    A: DataCollector,
+    B: DataCollector<Env = A::Env>,
+    <A::Env as Validate>::Action: Clone,
+{
+    type Env = A::Env;
+
+    fn begin_episode(&mut self, env: &Self::Env) {
+        self.0.begin_episode(env);
+        self.1.begin_episode(env);
+    }
+
+    fn push_result(
+        &mut self,
+        env: &Self::Env,
+        action: <Self::Env as Validate>::Action,
+        reward: f32,
+    ) {
+        self.0.push_result(env, action.clone(), reward);
+        self.1.push_result(env, action, reward);
+    }
+
+    fn end_episode(&mut self, env: &Self::Env) {
+        self.0.end_episode(env);
+        self.1.end_episode(env);
+    }
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct DataPoint<S, A, D> {
     state: S,
     transition: Transition<A>,
@@ -41,6 +81,7 @@ impl<S, A, D> DataPoint<S, A, D> {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Transition<A> {
     First { len: usize },
     Trans { action: A, reward: f32 },
@@ -0,0 +1,359 @@
+use crate::enviroment::Validate;
+use crate::manager::Summary;
+
+use super::{DataCollector, DataPoint, Transition};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use std::io::{self, BufRead, Write};
+use std::marker::PhantomData;
+
+/// A data collector which writes every episode to a writer as a line of json.
+/// Episodes use the same layout as the other collectors, starting with a `Transition::First` node.
+pub struct Recorder<F, E, S, W>
+where
+    F: Fn(&E) -> S,
+    E: Validate,
+    W: Write,
+{
+    writer: W,
+    func: F,
+    episode: Vec<DataPoint<S, E::Action, ()>>,
+    error: Option<io::Error>,
+    marker: PhantomData<*const E>,
+}
+
+impl<F, E, S, W> Recorder<F, E, S, W>
+where
+    F: Fn(&E) -> S,
+    E: Validate,
+    E::Action: Serialize,
+    S: Serialize,
+    W: Write,
+{
+    pub fn new(writer: W, func: F) -> Self {
+        Self {
+            writer,
+            func,
+            episode: Vec::new(),
+            error: None,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn func(&self) -> &F {
+        &self.func
+    }
+
+    /// Returns the first error encountered while writing, if any
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    /// Write the unfinished episode, if there is one, and return the writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write(false);
+        match self.error.take() {
+            Some(err) => Err(err),
+            None => {
+                self.writer.flush()?;
+                Ok(self.writer)
+            }
+        }
+    }
+
+    fn write(&mut self, terminal: bool) {
+        if self.episode.is_empty() || self.error.is_some() {
+            self.episode.clear();
+            return;
+        }
+
+        let record = RecordRef {
+            data: &self.episode,
+            terminal,
+        };
+        let res = serde_json::to_writer(&mut self.writer, &record)
+            .map_err(io::Error::from)
+            .and_then(|_| self.writer.write_all(b"\n"));
+        if let Err(err) = res {
+            self.error.replace(err);
+        }
+        self.episode.clear();
+    }
+}
+
+impl<F, E, S, W> DataCollector for Recorder<F, E, S, W>
+where
+    F: Fn(&E) -> S,
+    E: Validate,
+    E::Action: Serialize,
+    S: Serialize,
+    W: Write,
+{
+    type Env = E;
+
+    fn begin_episode(&mut self, env: &Self::Env) {
+        // an episode which didn't end is written as non-terminal
+        self.write(false);
+        self.episode.push(DataPoint {
+            state: (self.func)(env),
+            transition: Transition::First { len: 1 },
+            data: (),
+        });
+    }
+
+    fn push_result(
+        &mut self,
+        env: &Self::Env,
+        action: <Self::Env as Validate>::Action,
+        reward: f32,
+    ) {
+        match self.episode.first_mut() {
+            Some(DataPoint {
+                transition: Transition::First { len },
+                ..
+            }) => *len += 1,
+            _ => panic!("Episode wasn't started"),
+        }
+        self.episode.push(DataPoint {
+            state: (self.func)(env),
+            transition: Transition::Trans { action, reward },
+            data: (),
+        });
+    }
+
+    fn end_episode(&mut self, _env: &Self::Env) {
+        self.write(true);
+    }
+}
+
+#[derive(Serialize)]
+struct RecordRef<'a, S, A> {
+    data: &'a [DataPoint<S, A, ()>],
+    terminal: bool,
+}
+
+/// A single recorded episode
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Record<S, A> {
+    data: Vec<DataPoint<S, A, ()>>,
+    terminal: bool,
+}
+
+impl<S, A> Record<S, A> {
+    /// Iterate over the data points of the episode, like `Episode` does
+    pub fn iter(&self) -> std::slice::Iter<'_, DataPoint<S, A, ()>> {
+        self.data.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Returns whether the episode reached a terminal state
+    pub fn is_terminal(&self) -> bool {
+        self.terminal
+    }
+
+    pub fn into_data(self) -> Vec<DataPoint<S, A, ()>> {
+        self.data
+    }
+}
+
+impl<'a, S, A> IntoIterator for &'a Record<S, A> {
+    type Item = &'a DataPoint<S, A, ()>;
+
+    type IntoIter = std::slice::Iter<'a, DataPoint<S, A, ()>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Reads episodes written by a `Recorder`
+pub struct Reader<R, S, A> {
+    reader: R,
+    line: String,
+    marker: PhantomData<*const (S, A)>,
+}
+
+impl<R, S, A> Reader<R, S, A>
+where
+    R: BufRead,
+    S: DeserializeOwned,
+    A: DeserializeOwned,
+{
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: String::new(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<R, S, A> Iterator for Reader<R, S, A>
+where
+    R: BufRead,
+    S: DeserializeOwned,
+    A: DeserializeOwned,
+{
+    type Item = io::Result<Record<S, A>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        read_line(&mut self.reader, &mut self.line)
+    }
+}
+
+/// Every action of a whole game, like the one in a manager's `Summary`.
+/// Unlike a `Record`, which only holds what a single agent saw, it's enough to replay the game.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Game<A> {
+    pub actions: Vec<A>,
+}
+
+impl<A: Clone> Game<A> {
+    pub fn from_summary<S>(summary: &Summary<S, A>) -> Self {
+        Self {
+            actions: summary.actions.clone(),
+        }
+    }
+}
+
+impl<A: Serialize> Game<A> {
+    /// Write the game as a line of json, which can be read back by a `GameReader`
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        serde_json::to_writer(&mut writer, self).map_err(io::Error::from)?;
+        writer.write_all(b"\n")
+    }
+}
+
+/// Reads games written by `Game::write`
+pub struct GameReader<R, A> {
+    reader: R,
+    line: String,
+    marker: PhantomData<*const A>,
+}
+
+impl<R, A> GameReader<R, A>
+where
+    R: BufRead,
+    A: DeserializeOwned,
+{
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: String::new(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<R, A> Iterator for GameReader<R, A>
+where
+    R: BufRead,
+    A: DeserializeOwned,
+{
+    type Item = io::Result<Game<A>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        read_line(&mut self.reader, &mut self.line)
+    }
+}
+
+/// Parse the next non-empty line of json
+fn read_line<R: BufRead, T: DeserializeOwned>(
+    reader: &mut R,
+    line: &mut String,
+) -> Option<io::Result<T>> {
+    loop {
+        line.clear();
+        match reader.read_line(line) {
+            Ok(0) => return None,
+            Ok(_) if line.trim().is_empty() => continue,
+            Ok(_) => return Some(serde_json::from_str(line).map_err(io::Error::from)),
+            Err(err) => return Some(Err(err)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enviroment::Enviroment;
+
+    /// Sums the actions until the sum reaches three
+    struct Counter {
+        sum: u32,
+    }
+
+    impl Validate for Counter {
+        type Action = u32;
+
+        fn validate(&self, _action: u32) -> bool {
+            true
+        }
+    }
+
+    impl Enviroment for Counter {
+        type Status = bool;
+
+        fn reset(&mut self) {
+            self.sum = 0;
+        }
+
+        fn step(&mut self, action: u32) -> (bool, f32) {
+            self.sum += action;
+            (self.sum >= 3, action as f32)
+        }
+    }
+
+    fn sum(env: &Counter) -> u32 {
+        env.sum
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut env = Counter { sum: 0 };
+        let mut recorder = Recorder::new(Vec::new(), sum);
+        recorder.begin_episode(&env);
+        for action in [1, 2].iter() {
+            env.step(*action);
+            recorder.push_result(&env, *action, *action as f32);
+        }
+        recorder.end_episode(&env);
+        // an unfinished episode
+        env.reset();
+        recorder.begin_episode(&env);
+        let written = recorder.finish().expect("Writing to a vec can't fail");
+
+        let records = Reader::<_, u32, u32>::new(&written[..])
+            .collect::<io::Result<Vec<_>>>()
+            .expect("The records should be readable");
+        assert_eq!(records.len(), 2);
+
+        let record = &records[0];
+        assert!(record.is_terminal());
+        assert_eq!(record.len(), 3);
+        match record.iter().next().map(DataPoint::transition) {
+            Some(Transition::First { len: 3 }) => (),
+            _ => panic!("The episode should start with its length"),
+        }
+        let steps = record
+            .iter()
+            .skip(1)
+            .map(|point| match point.transition() {
+                Transition::Trans { action, reward } => (*action, *reward, *point.state()),
+                Transition::First { .. } => panic!("Unexpected first node"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(steps, vec![(1, 1., 1), (2, 2., 3)]);
+
+        assert!(!records[1].is_terminal());
+        assert_eq!(records[1].len(), 1);
+    }
+}
@@ -0,0 +1,143 @@
+use crate::enviroment::Validate;
+
+use super::mem_buffer::Episodes;
+use super::{DataCollector, DataPoint, Transition};
+
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+
+/// A data collector for on-policy agents.
+/// It becomes full after collecting `len` transitions and is cleared by the next push,
+/// so the agent should train on it as soon as `is_full` returns true.
+/// An episode which was cut off continues in the next rollout, starting from its last state.
+/// Only episodes which were ended with `end_episode` count as terminal,
+/// so an episode which was truncated can still be bootstrapped from its last state.
+pub struct Rollout<F, E, S, D>
+where
+    F: Fn(&E) -> S,
+    E: Validate,
+{
+    buffer: VecDeque<DataPoint<S, E::Action, D>>,
+    func: F,
+    head: usize,
+    len: usize,
+    steps: usize,
+    /// Whether each episode of the rollout has reached a terminal state
+    terminal: Vec<bool>,
+    marker: PhantomData<*const E>,
+}
+
+impl<F, E, S, D> Rollout<F, E, S, D>
+where
+    F: Fn(&E) -> S,
+    E: Validate,
+    D: Default,
+{
+    pub fn new(len: usize, func: F) -> Self {
+        Self {
+            buffer: VecDeque::with_capacity(len + 1),
+            func,
+            head: 0,
+            len,
+            steps: 0,
+            terminal: Vec::new(),
+            marker: PhantomData,
+        }
+    }
+
+    pub fn func(&self) -> &F {
+        &self.func
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.steps >= self.len
+    }
+
+    /// Returns the number of transitions in the rollout
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Returns whether the `episode`th episode of the rollout has reached a terminal state
+    pub fn is_terminal(&self, episode: usize) -> bool {
+        self.terminal.get(episode).copied().unwrap_or(false)
+    }
+
+    pub fn episodes<'a>(&'a self) -> Episodes<'a, S, E::Action, D> {
+        Episodes::new(&self.buffer)
+    }
+
+    /// Returns the data point of the latest state, which is the one agents act in
+    pub fn latest(&self) -> Option<&DataPoint<S, E::Action, D>> {
+        self.buffer.back()
+    }
+
+    /// Start a new rollout in the middle of an episode
+    fn clear(&mut self) {
+        let last = self.buffer.pop_back();
+        self.buffer.clear();
+        self.head = 0;
+        self.steps = 0;
+        self.terminal.clear();
+
+        if let Some(last) = last {
+            self.terminal.push(false);
+            self.buffer.push_back(DataPoint {
+                state: last.state,
+                transition: Transition::First { len: 1 },
+                data: last.data,
+            });
+        }
+    }
+}
+
+impl<F, E, S, D> DataCollector for Rollout<F, E, S, D>
+where
+    F: Fn(&E) -> S,
+    E: Validate,
+    D: Default,
+{
+    type Env = E;
+
+    fn begin_episode(&mut self, env: &Self::Env) {
+        if self.is_full() {
+            self.buffer.clear();
+            self.steps = 0;
+            self.terminal.clear();
+        }
+        self.terminal.push(false);
+        self.head = self.buffer.len();
+        self.buffer.push_back(DataPoint {
+            state: (self.func)(env),
+            transition: Transition::First { len: 1 },
+            data: Default::default(),
+        });
+    }
+
+    fn push_result(
+        &mut self,
+        env: &Self::Env,
+        action: <Self::Env as Validate>::Action,
+        reward: f32,
+    ) {
+        if self.is_full() {
+            self.clear();
+        }
+        match &mut self.buffer[self.head].transition {
+            Transition::First { len } => *len += 1,
+            Transition::Trans { .. } => panic!("Head does not point to first data node"),
+        }
+        self.buffer.push_back(DataPoint {
+            state: (self.func)(env),
+            transition: Transition::Trans { action, reward },
+            data: Default::default(),
+        });
+        self.steps += 1;
+    }
+
+    fn end_episode(&mut self, _env: &Self::Env) {
+        if let Some(terminal) = self.terminal.last_mut() {
+            *terminal = true;
+        }
+    }
+}
@@ -1,54 +1,206 @@
 pub mod data_collector;
+pub mod replay;
+pub mod runner;
+pub mod self_play;
+pub mod simultaneous;
+pub mod tournament;
 
-use crate::agent::{Agent, AgentBuilder};
-use crate::enviroment::{EnvBuilder, Enviroment, IsTerminal, PlayerRange, SingleWinner};
+use crate::agent::{Agent, AgentBuilder, Freeze};
+use crate::enviroment::{
+    AssignRewards, EnvBuilder, Enviroment, IsTerminal, Observe, Observed, PlayerRange, Render,
+    Simultaneous, Validate,
+};
+use crate::error::{self, Error, Result};
+use crate::misc::{random, Seeder};
+
+use random_fast_rng::FastRng;
+
+use std::marker::PhantomData;
 
 use data_collector::DataCollector;
+use simultaneous::SimultaneousManager;
 
 pub struct ManagerBuilder<E: EnvBuilder + PlayerRange>
 where
-    <E::Output as Enviroment>::Action: Clone,
+    <E::Output as Validate>::Action: Clone,
     <E::Output as Enviroment>::Status: IsTerminal,
-    E::Output: SingleWinner,
 {
     agents: Vec<Box<dyn AgentWrapper<Env = E::Output>>>,
     env: E,
+    turn_order: TurnOrder,
+    seeder: Option<Seeder>,
+    render: Option<Renderer<E::Output>>,
 }
 
 impl<E: EnvBuilder + PlayerRange> ManagerBuilder<E>
 where
-    <E::Output as Enviroment>::Action: Clone,
+    <E::Output as Validate>::Action: Clone,
     <E::Output as Enviroment>::Status: IsTerminal,
-    E::Output: SingleWinner,
 {
     pub fn new(env: E) -> Self {
         Self {
             agents: Vec::new(),
             env,
+            turn_order: TurnOrder::Fixed,
+            seeder: None,
+            render: None,
         }
     }
 
-    pub fn add_agent<A>(&mut self, agent: A)
+    /// Print or capture the render of the enviroment after every step
+    pub fn render(&mut self, mode: RenderMode)
+    where
+        E::Output: Render,
+    {
+        self.render.replace(Renderer {
+            mode,
+            render: <E::Output as Render>::render,
+        });
+    }
+
+    /// Derive the seeds of the enviroment, every agent and the turn order from a single seed.
+    /// It overrides the seed of `TurnOrder::Shuffle` and has to be set before adding agents.
+    pub fn seed(&mut self, seed: u64) -> Result<()> {
+        error::ensure(
+            self.agents.is_empty(),
+            "seed",
+            "has to be set before adding agents",
+        )?;
+        let mut seeder = Seeder::new(seed);
+        self.env.set_seed(seeder.next_seed());
+        self.seeder.replace(seeder);
+        Ok(())
+    }
+
+    /// A stream of seeds derived from the master seed, separate from the ones of the enviroment and the agents.
+    /// Returns `None` if the manager isn't seeded.
+    pub fn fork_seeder(&mut self) -> Option<Seeder> {
+        self.seeder.as_mut().map(Seeder::fork)
+    }
+
+    /// The order in which agents take turns, `TurnOrder::Fixed` by default
+    pub fn turn_order(&mut self, turn_order: TurnOrder) {
+        self.turn_order = turn_order;
+    }
+
+    pub fn add_agent<A>(&mut self, agent: A) -> Result<()>
     where
         A: AgentBuilder<E>,
         A::Agent: Agent<Env = E::Output> + 'static,
         A::Data: DataCollector<Env = E::Output>,
     {
-        let (agent, data_collect) = agent.build(&mut self.env);
+        let (agent, data_collect) = self.build_agent(agent)?;
         let agent = Box::new(Wrapper::new(agent, data_collect));
         self.agents
             .push(agent as Box<dyn AgentWrapper<Env = <E as EnvBuilder>::Output>>);
+        Ok(())
     }
 
-    pub fn build(self) -> Option<Manager<E::Output>> {
-        let len = self.agents.len();
-        if len >= E::MIN && len <= E::MAX.unwrap_or(usize::MAX) {
-            Some(Manager {
-                agents: self.agents,
-                env: self.env.build(),
-            })
+    /// Add an agent which can be frozen into snapshots, as needed by `SelfPlay`
+    pub fn add_learner<A>(&mut self, agent: A) -> Result<()>
+    where
+        A: AgentBuilder<E>,
+        A::Agent: Freeze + Agent<Env = E::Output> + 'static,
+        A::Data: DataCollector<Env = E::Output>,
+        <<A::Agent as Freeze>::Frozen as Agent>::Data: 'static,
+    {
+        let (agent, data_collect) = self.build_agent(agent)?;
+        let agent = Box::new(FreezeWrapper(Wrapper::new(agent, data_collect)));
+        self.agents
+            .push(agent as Box<dyn AgentWrapper<Env = <E as EnvBuilder>::Output>>);
+        Ok(())
+    }
+
+    /// Add an agent which only sees its own observations of the enviroment.
+    /// The agent plays as the player with the same index as the agent, so tokens have to be handed out in order.
+    pub fn add_observer<A>(&mut self, agent: A) -> Result<()>
+    where
+        E::Output: Observe + 'static,
+        A: AgentBuilder<E>,
+        A::Agent: Agent<Env = Observed<<E::Output as Observe>::Observation>> + 'static,
+        A::Data: DataCollector<Env = Observed<<E::Output as Observe>::Observation>>,
+    {
+        let player = self.agents.len() as u32;
+        let (agent, data_collect) = self.build_agent(agent)?;
+        let agent = Box::new(ObservingWrapper::new(
+            Wrapper::new(agent, data_collect),
+            player,
+        ));
+        self.agents
+            .push(agent as Box<dyn AgentWrapper<Env = <E as EnvBuilder>::Output>>);
+        Ok(())
+    }
+
+    /// Fails if the enviroment doesn't accept the number of added agents
+    pub fn build(self) -> Result<Manager<E::Output>>
+    where
+        E::Output: AssignRewards,
+    {
+        Self::check_players(self.agents.len())?;
+        Ok(self.finish())
+    }
+
+    /// Build the manager without checking the number of agents, for managers whose agents are swapped in later
+    fn finish(self) -> Manager<E::Output>
+    where
+        E::Output: AssignRewards,
+    {
+        let mut seeder = self.seeder;
+        let seed = match (&mut seeder, self.turn_order) {
+            (Some(seeder), _) => seeder.next_seed(),
+            (None, TurnOrder::Shuffle(seed)) => seed,
+            (None, _) => 0,
+        };
+        Manager {
+            agents: self.agents,
+            env: self.env.build(),
+            turn_order: self.turn_order,
+            episodes: 0,
+            rng: FastRng::seed(seed, 0),
+            seeder,
+            render: self.render,
+        }
+    }
+
+    /// Build a manager for enviroments in which all agents act at the same time.
+    /// The turn order is ignored.
+    pub fn build_simultaneous(self) -> Result<SimultaneousManager<E::Output>>
+    where
+        E::Output: Simultaneous,
+    {
+        Self::check_players(self.agents.len())?;
+        Ok(SimultaneousManager::new(
+            self.agents,
+            self.env.build(),
+            self.render,
+        ))
+    }
+
+    /// Fails with `Error::PlayerCount` if the enviroment is already full, before the agent takes a token
+    fn build_agent<A: AgentBuilder<E>>(&mut self, mut agent: A) -> Result<(A::Agent, A::Data)> {
+        let count = self.agents.len() + 1;
+        if E::MAX.is_some_and(|max| count > max) {
+            return Err(Error::PlayerCount {
+                count,
+                min: E::MIN,
+                max: E::MAX,
+            });
+        }
+        if let Some(seeder) = &mut self.seeder {
+            agent.set_seed(seeder.next_seed());
+        }
+        agent.build(&mut self.env)
+    }
+
+    fn check_players(count: usize) -> Result<()> {
+        if count >= E::MIN && count <= E::MAX.unwrap_or(usize::MAX) {
+            Ok(())
         } else {
-            None
+            Err(Error::PlayerCount {
+                count,
+                min: E::MIN,
+                max: E::MAX,
+            })
         }
     }
 }
@@ -56,61 +208,277 @@ where
 pub struct Manager<E>
 where
     E: Enviroment,
+    E::Action: Clone,
     E::Status: IsTerminal,
-    E: SingleWinner,
+    E: AssignRewards,
 {
     agents: Vec<Box<dyn AgentWrapper<Env = E>>>,
     env: E,
+    turn_order: TurnOrder,
+    episodes: usize,
+    rng: FastRng,
+    seeder: Option<Seeder>,
+    render: Option<Renderer<E>>,
 }
 
 impl<E> Manager<E>
 where
     E: Enviroment,
+    E::Action: Clone,
     E::Status: IsTerminal,
-    E: SingleWinner,
+    E: AssignRewards,
 {
-    pub fn episode(&mut self) -> E::Status {
+    pub fn episode(&mut self) -> Summary<E::Status, E::Action> {
         self.env.reset();
+        for agent in self.agents.iter_mut() {
+            agent.begin_episode();
+        }
+        let seats = self.seats();
+        let mut rewards = vec![0.; self.agents.len()];
+        let mut frames = Vec::new();
+        let mut actions = Vec::new();
         loop {
-            for (i, agent) in self.agents.iter_mut().enumerate() {
-                let act = agent.action(&mut self.env);
+            for &i in seats.iter() {
+                let act = self.agents[i].action(&self.env);
+                actions.push(act.clone());
                 let (status, reward) = self.env.step(act);
-                agent.push_result(reward);
+                if let Some(renderer) = &self.render {
+                    renderer.frame(&self.env, &mut frames);
+                }
+                self.agents[i].push_result(reward);
+                rewards[i] += reward;
 
                 if status.is_terminal() {
-                    for x in 0..i {
-                        self.agents[x].end_episode(&self.env, E::LOSS);
+                    let last = self.env.final_rewards(&status, i, self.agents.len());
+                    assert_eq!(
+                        last.len(),
+                        self.agents.len(),
+                        "Expected a reward for every agent"
+                    );
+                    for (x, agent) in self.agents.iter_mut().enumerate() {
+                        agent.end_episode(&self.env, last[x]);
+                        rewards[x] += last[x];
                     }
+                    return Summary {
+                        status,
+                        rewards,
+                        seats,
+                        frames,
+                        actions,
+                    };
+                }
+            }
+        }
+    }
 
-                    self.agents[i].end_episode(&self.env, 0.);
-                    
-                    for x in i + 1..self.agents.len() {
-                        self.agents[x].end_episode(&self.env, E::LOSS);
-                    }
-                    return status;
+    /// Returns the indices of the agents in the order they will move in the next episode
+    fn seats(&mut self) -> Vec<usize> {
+        let len = self.agents.len();
+        let mut seats = (0..len).collect::<Vec<_>>();
+        match self.turn_order {
+            TurnOrder::Fixed => (),
+            TurnOrder::Rotate => seats.rotate_left(self.episodes % len),
+            TurnOrder::Shuffle(_) => {
+                for i in (1..len).rev() {
+                    seats.swap(i, random::below(&mut self.rng, i + 1));
                 }
             }
         }
+        self.episodes += 1;
+        seats
+    }
+
+    pub fn agents(&self) -> &[Box<dyn AgentWrapper<Env = E>>] {
+        &self.agents
+    }
+
+    /// Swap out the agents, which lets them change between episodes without rebuilding the enviroment
+    pub fn replace_agents(
+        &mut self,
+        agents: Vec<Box<dyn AgentWrapper<Env = E>>>,
+    ) -> Vec<Box<dyn AgentWrapper<Env = E>>> {
+        std::mem::replace(&mut self.agents, agents)
+    }
+
+    /// Like `ManagerBuilder::fork_seeder`, for components built around the manager
+    pub fn fork_seeder(&mut self) -> Option<Seeder> {
+        self.seeder.as_mut().map(Seeder::fork)
+    }
+
+    /// Play `episodes` episodes with every agent in evaluation mode and count the results of `agent`
+    pub fn evaluate(&mut self, agent: usize, episodes: usize) -> Result<Evaluation> {
+        error::ensure(
+            agent < self.agents.len(),
+            "agent",
+            "agent index out of bounds",
+        )?;
+        error::ensure(episodes > 0, "episodes", "must be at least 1")?;
+        for wrapper in self.agents.iter_mut() {
+            wrapper.set_eval(true);
+        }
+
+        let mut evaluation = Evaluation::default();
+        for _ in 0..episodes {
+            match self.episode().outcome(agent) {
+                Outcome::Win => evaluation.wins += 1,
+                Outcome::Draw => evaluation.draws += 1,
+                Outcome::Loss => evaluation.losses += 1,
+            }
+        }
+
+        for wrapper in self.agents.iter_mut() {
+            wrapper.set_eval(false);
+        }
+        Ok(evaluation)
+    }
+}
+
+/// What the manager does with the render of the enviroment after each step
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderMode {
+    /// Print to the standard output
+    Print,
+    /// Keep the frames in the summary of the episode
+    Capture,
+}
+
+pub(crate) struct Renderer<E> {
+    mode: RenderMode,
+    render: fn(&E) -> String,
+}
+
+impl<E> Renderer<E> {
+    pub(crate) fn frame(&self, env: &E, frames: &mut Vec<String>) {
+        let frame = (self.render)(env);
+        match self.mode {
+            RenderMode::Print => println!("{}", frame),
+            RenderMode::Capture => frames.push(frame),
+        }
+    }
+}
+
+/// Decides which agent moves first in each episode
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TurnOrder {
+    /// Agents always move in the order they were added
+    Fixed,
+    /// Every episode the next agent in order moves first
+    Rotate,
+    /// Agents are shuffled every episode by a random generator with the given seed
+    Shuffle(u64),
+}
+
+/// The result of a single episode
+#[derive(Clone, Debug)]
+pub struct Summary<S, A> {
+    pub status: S,
+    /// The total reward received by each agent
+    pub rewards: Vec<f32>,
+    /// The index of the agent in each seat, in the order they moved
+    pub seats: Vec<usize>,
+    /// The render of the enviroment after every step, if the manager captures them
+    pub frames: Vec<String>,
+    /// Every action in the order it was taken, which is enough to replay the episode
+    pub actions: Vec<A>,
+}
+
+impl<S, A> Summary<S, A> {
+    /// An agent wins if it received more reward than all the others and draws if it's tied for the most
+    pub fn outcome(&self, agent: usize) -> Outcome {
+        let reward = self.rewards[agent];
+        let mut outcome = Outcome::Win;
+        for (i, other) in self.rewards.iter().enumerate() {
+            if i != agent {
+                if *other > reward {
+                    return Outcome::Loss;
+                } else if *other == reward {
+                    outcome = Outcome::Draw;
+                }
+            }
+        }
+        outcome
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Win,
+    Draw,
+    Loss,
+}
+
+/// The results of an agent over several episodes, its rates are `None` if no episode was played
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Evaluation {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+
+impl Evaluation {
+    pub fn episodes(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    pub fn win_rate(&self) -> Option<f32> {
+        self.rate(self.wins)
+    }
+
+    pub fn draw_rate(&self) -> Option<f32> {
+        self.rate(self.draws)
+    }
+
+    pub fn loss_rate(&self) -> Option<f32> {
+        self.rate(self.losses)
+    }
+
+    fn rate(&self, count: usize) -> Option<f32> {
+        match self.episodes() {
+            0 => None,
+            episodes => Some(count as f32 / episodes as f32),
+        }
     }
 }
 
 pub trait AgentWrapper {
-    type Env: Enviroment;
+    type Env: Validate;
 
-    fn action(&mut self, env: &Self::Env) -> <Self::Env as Enviroment>::Action;
+    fn action(&mut self, env: &Self::Env) -> <Self::Env as Validate>::Action;
 
-    fn begin_episode(&mut self, env: &Self::Env);
+    /// Called after the enviroment is reset, the data collector of the agent begins the episode when the agent first acts
+    fn begin_episode(&mut self);
 
     fn push_result(&mut self, reward: f32);
 
     fn end_episode(&mut self, env: &Self::Env, reward: f32);
+
+    /// Switch evaluation mode, in which the agent doesn't collect data or learn
+    fn set_eval(&mut self, eval: bool);
+
+    /// Move the agent to the seat of `player`, see `Agent::set_player`
+    fn set_player(&mut self, player: u32);
+
+    /// Returns the number of times the agent was updated
+    fn updates(&self) -> usize;
+
+    /// Returns a frozen copy of the agent in evaluation mode, if the agent supports it
+    fn snapshot(&self) -> Option<Box<dyn AgentWrapper<Env = Self::Env>>> {
+        None
+    }
 }
 
 pub struct Wrapper<A: Agent> {
     agent: A,
     data: A::Data,
     reward: Option<f32>,
-    action: Option<<A::Env as Enviroment>::Action>,
+    action: Option<<A::Env as Validate>::Action>,
+    /// Whether the data collector began the current episode, which happens when the agent first acts,
+    /// so that it starts from the state the agent moved in rather than the one after the reset
+    started: bool,
+    eval: bool,
+    /// Frozen agents stay in evaluation mode, since they can't learn
+    frozen: bool,
+    updates: usize,
 }
 
 impl<A: Agent> Wrapper<A> {
@@ -120,21 +488,42 @@ impl<A: Agent> Wrapper<A> {
             data,
             reward: None,
             action: None,
+            started: false,
+            eval: false,
+            frozen: false,
+            updates: 0,
         }
     }
+
+    /// Wrap a frozen agent, which ignores requests to leave evaluation mode
+    pub fn frozen(agent: A, data: A::Data) -> Self {
+        let mut wrapper = Self::new(agent, data);
+        wrapper.frozen = true;
+        wrapper.eval = true;
+        wrapper.agent.set_eval(true);
+        wrapper
+    }
 }
 
 impl<A: Agent> AgentWrapper for Wrapper<A>
 where
-    <A::Env as Enviroment>::Action: Clone,
+    <A::Env as Validate>::Action: Clone,
 {
     type Env = A::Env;
 
-    fn action(&mut self, env: &Self::Env) -> <Self::Env as Enviroment>::Action {
-        if let Some(reward) = self.reward.take() {
+    fn action(&mut self, env: &Self::Env) -> <Self::Env as Validate>::Action {
+        if self.eval {
+            return self.agent.action(env, &self.data);
+        }
+
+        if !self.started {
+            self.data.begin_episode(env);
+            self.started = true;
+        } else if let Some(reward) = self.reward.take() {
             if let Some(action) = self.action.take() {
                 self.data.push_result(env, action, reward);
                 self.agent.update(&self.data);
+                self.updates += 1;
             }
         }
 
@@ -143,10 +532,10 @@ where
         act
     }
 
-    fn begin_episode(&mut self, env: &Self::Env) {
+    fn begin_episode(&mut self) {
         self.reward.take();
         self.action.take();
-        self.data.begin_episode(env);
+        self.started = false;
     }
 
     fn push_result(&mut self, reward: f32) {
@@ -154,9 +543,362 @@ where
     }
 
     fn end_episode(&mut self, env: &Self::Env, reward: f32) {
-        let action = self.action.take().expect("Cached action missing");
-        let reward = self.reward.expect("Cached reward missing") + reward;
-        self.data.push_result(env, action, reward);
+        // agents which didn't get to act before the episode ended have nothing to learn from
+        if self.eval || !self.started {
+            return;
+        }
+        let reward = self.reward.take().unwrap_or(0.) + reward;
+        if let Some(action) = self.action.take() {
+            self.data.push_result(env, action, reward);
+        }
+        self.data.end_episode(env);
         self.agent.update(&self.data);
+        self.updates += 1;
+        self.agent.end_episode(&self.data);
+    }
+
+    fn set_eval(&mut self, eval: bool) {
+        self.eval = eval || self.frozen;
+        self.agent.set_eval(self.eval);
+    }
+
+    fn set_player(&mut self, player: u32) {
+        self.agent.set_player(player);
+    }
+
+    fn updates(&self) -> usize {
+        self.updates
+    }
+}
+
+/// Wraps agents which can be frozen, so the manager can take snapshots of them
+pub struct FreezeWrapper<A: Freeze>(Wrapper<A>);
+
+impl<A: Freeze> AgentWrapper for FreezeWrapper<A>
+where
+    <A::Env as Validate>::Action: Clone,
+    <A::Frozen as Agent>::Data: 'static,
+{
+    type Env = A::Env;
+
+    fn action(&mut self, env: &Self::Env) -> <Self::Env as Validate>::Action {
+        self.0.action(env)
+    }
+
+    fn begin_episode(&mut self) {
+        self.0.begin_episode()
+    }
+
+    fn push_result(&mut self, reward: f32) {
+        self.0.push_result(reward)
+    }
+
+    fn end_episode(&mut self, env: &Self::Env, reward: f32) {
+        self.0.end_episode(env, reward)
+    }
+
+    fn set_eval(&mut self, eval: bool) {
+        self.0.set_eval(eval)
+    }
+
+    fn set_player(&mut self, player: u32) {
+        self.0.set_player(player)
+    }
+
+    fn updates(&self) -> usize {
+        self.0.updates()
+    }
+
+    fn snapshot(&self) -> Option<Box<dyn AgentWrapper<Env = Self::Env>>> {
+        let (agent, data) = self.0.agent.freeze(&self.0.data);
+        Some(Box::new(Wrapper::frozen(agent, data)))
+    }
+}
+
+/// Shows the agent only the observation of its player instead of the whole enviroment
+pub struct ObservingWrapper<A: Agent, E> {
+    inner: Wrapper<A>,
+    player: u32,
+    phantom: PhantomData<*const E>,
+}
+
+impl<A: Agent, E> ObservingWrapper<A, E> {
+    pub fn new(inner: Wrapper<A>, player: u32) -> Self {
+        Self {
+            inner,
+            player,
+            phantom: PhantomData,
+        }
+    }
+
+    fn observe(&self, env: &E) -> Observed<E::Observation>
+    where
+        E: Observe,
+    {
+        Observed(env.observe(self.player))
+    }
+}
+
+impl<A: Agent, E> AgentWrapper for ObservingWrapper<A, E>
+where
+    A: Agent<Env = Observed<E::Observation>>,
+    E: Observe,
+    E::Action: Clone,
+{
+    type Env = E;
+
+    fn action(&mut self, env: &Self::Env) -> <Self::Env as Validate>::Action {
+        self.inner.action(&self.observe(env))
+    }
+
+    fn begin_episode(&mut self) {
+        self.inner.begin_episode()
+    }
+
+    fn push_result(&mut self, reward: f32) {
+        self.inner.push_result(reward)
+    }
+
+    fn end_episode(&mut self, env: &Self::Env, reward: f32) {
+        self.inner.end_episode(&self.observe(env), reward)
+    }
+
+    fn set_eval(&mut self, eval: bool) {
+        self.inner.set_eval(eval)
+    }
+
+    fn set_player(&mut self, player: u32) {
+        self.player = player;
+        self.inner.set_player(player)
+    }
+
+    fn updates(&self) -> usize {
+        self.inner.updates()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::doubles::{
+        Entry, Log, PairBuilder, PeekBuilder, ScriptBuilder, Seen, Tally, TallyBuilder,
+    };
+
+    fn summary(rewards: Vec<f32>) -> Summary<(), ()> {
+        Summary {
+            status: (),
+            seats: (0..rewards.len()).collect(),
+            rewards,
+            frames: Vec::new(),
+            actions: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn most_reward_wins() {
+        let summary = summary(vec![1., -1., 0.]);
+        assert_eq!(summary.outcome(0), Outcome::Win);
+        assert_eq!(summary.outcome(1), Outcome::Loss);
+        assert_eq!(summary.outcome(2), Outcome::Loss);
+    }
+
+    #[test]
+    fn ties_for_the_most_reward_draw() {
+        let summary = summary(vec![1., 1., 0.]);
+        assert_eq!(summary.outcome(0), Outcome::Draw);
+        assert_eq!(summary.outcome(1), Outcome::Draw);
+        assert_eq!(summary.outcome(2), Outcome::Loss);
+    }
+
+    #[test]
+    fn a_single_agent_always_wins() {
+        assert_eq!(summary(vec![-5.]).outcome(0), Outcome::Win);
+    }
+
+    #[test]
+    fn rates_need_episodes() {
+        let evaluation = Evaluation {
+            wins: 1,
+            draws: 2,
+            losses: 1,
+        };
+        assert_eq!(evaluation.win_rate(), Some(0.25));
+        assert_eq!(evaluation.draw_rate(), Some(0.5));
+        assert_eq!(Evaluation::default().win_rate(), None);
+    }
+
+    #[test]
+    fn agents_record_the_states_they_moved_in() {
+        let (first, second) = (Log::default(), Log::default());
+        let mut builder = ManagerBuilder::new(TallyBuilder::new(4));
+        for (action, log) in [(1, &first), (0, &second)].iter() {
+            let actions = vec![*action];
+            let log = (*log).clone();
+            builder
+                .add_agent(ScriptBuilder { actions, log })
+                .expect("The agents fit the enviroment");
+        }
+        let mut manager = builder.build().expect("The agents fit the enviroment");
+        manager.episode();
+
+        // the states count the moves, the first agent wins with two points and gets the final reward
+        assert_eq!(
+            first.entries(),
+            vec![
+                Entry::Begin(vec![1., 0.]),
+                Entry::Result(vec![1., 2.], 1, 1.),
+                Entry::Result(vec![1., 4.], 1, 2.),
+                Entry::End,
+            ]
+        );
+        assert_eq!(
+            second.entries(),
+            vec![
+                Entry::Begin(vec![1., 1.]),
+                Entry::Result(vec![1., 3.], 0, 0.),
+                Entry::Result(vec![1., 4.], 0, 0.),
+                Entry::End,
+            ]
+        );
+    }
+
+    /// Three agents in a game of three moves, so every agent moves once
+    fn seated(turn_order: TurnOrder, seed: Option<u64>) -> Manager<Tally> {
+        let mut builder = ManagerBuilder::new(TallyBuilder::new(3));
+        if let Some(seed) = seed {
+            builder.seed(seed).expect("No agents were added yet");
+        }
+        builder.turn_order(turn_order);
+        for _ in 0..3 {
+            builder
+                .add_agent(ScriptBuilder {
+                    actions: vec![1],
+                    log: Log::default(),
+                })
+                .expect("The agents fit the enviroment");
+        }
+        builder.build().expect("The agents fit the enviroment")
+    }
+
+    fn orders(manager: &mut Manager<Tally>, episodes: usize) -> Vec<Vec<usize>> {
+        (0..episodes)
+            .map(|_| {
+                let summary = manager.episode();
+                let players = summary
+                    .actions
+                    .iter()
+                    .map(|a| a.player as usize)
+                    .collect::<Vec<_>>();
+                assert_eq!(players, summary.seats, "Agents have to move in their seats");
+                summary.seats
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fixed_turns_follow_the_order_agents_were_added() {
+        let mut manager = seated(TurnOrder::Fixed, None);
+        assert_eq!(orders(&mut manager, 3), vec![vec![0, 1, 2]; 3]);
+    }
+
+    #[test]
+    fn rotating_turns_move_the_first_seat_along() {
+        let mut manager = seated(TurnOrder::Rotate, None);
+        assert_eq!(
+            orders(&mut manager, 4),
+            vec![vec![0, 1, 2], vec![1, 2, 0], vec![2, 0, 1], vec![0, 1, 2]]
+        );
+    }
+
+    #[test]
+    fn shuffled_turns_are_seeded_permutations() {
+        let shuffled = orders(&mut seated(TurnOrder::Shuffle(7), None), 20);
+        for seats in shuffled.iter() {
+            let mut sorted = seats.clone();
+            sorted.sort_unstable();
+            assert_eq!(sorted, vec![0, 1, 2]);
+        }
+        assert!(shuffled.iter().any(|seats| *seats != shuffled[0]));
+        assert_eq!(
+            orders(&mut seated(TurnOrder::Shuffle(7), None), 20),
+            shuffled
+        );
+
+        // the master seed overrides the one of the turn order
+        assert_eq!(
+            orders(&mut seated(TurnOrder::Shuffle(1), Some(3)), 20),
+            orders(&mut seated(TurnOrder::Shuffle(2), Some(3)), 20)
+        );
+    }
+
+    #[test]
+    fn final_rewards_can_have_several_winners() {
+        let mut builder = ManagerBuilder::new(TallyBuilder::new(3));
+        for action in [1, 0, 1].iter() {
+            builder
+                .add_agent(ScriptBuilder {
+                    actions: vec![*action],
+                    log: Log::default(),
+                })
+                .expect("The agents fit the enviroment");
+        }
+        let mut manager = builder.build().expect("The agents fit the enviroment");
+        let summary = manager.episode();
+
+        // the final rewards are added to the ones of every step
+        assert_eq!(summary.rewards, vec![2., 0., 2.]);
+        assert_eq!(summary.outcome(0), Outcome::Draw);
+        assert_eq!(summary.outcome(1), Outcome::Loss);
+        assert_eq!(summary.outcome(2), Outcome::Draw);
+    }
+
+    #[test]
+    fn observers_only_see_their_own_total() {
+        let seen = Seen::default();
+        let mut builder = ManagerBuilder::new(TallyBuilder::new(4));
+        builder
+            .add_observer(PeekBuilder { seen: seen.clone() })
+            .expect("The agents fit the enviroment");
+        builder
+            .add_agent(ScriptBuilder {
+                actions: vec![1],
+                log: Log::default(),
+            })
+            .expect("The agents fit the enviroment");
+        let mut manager = builder.build().expect("The agents fit the enviroment");
+        assert_eq!(manager.episode().rewards, vec![3., 3.]);
+
+        let seen = seen
+            .sights()
+            .iter()
+            .map(|sight| (sight.total, sight.moves))
+            .collect::<Vec<_>>();
+        assert_eq!(seen, vec![(0, 0), (1, 2), (2, 4)]);
+    }
+
+    #[test]
+    fn full_enviroments_reject_agents_before_they_take_a_token() {
+        let mut builder = ManagerBuilder::new(PairBuilder(TallyBuilder::new(2)));
+        for _ in 0..2 {
+            builder
+                .add_agent(ScriptBuilder {
+                    actions: vec![1],
+                    log: Log::default(),
+                })
+                .expect("The agents fit the enviroment");
+        }
+        let third = builder.add_agent(ScriptBuilder {
+            actions: vec![1],
+            log: Log::default(),
+        });
+        match third {
+            Err(Error::PlayerCount {
+                count: 3,
+                max: Some(2),
+                ..
+            }) => (),
+            _ => panic!("Expected the third agent to be rejected"),
+        }
+        assert!(builder.build().is_ok());
     }
 }
@@ -0,0 +1,125 @@
+//! Stepping through recorded games, like the ones written by `Game::write`, one move at a time
+
+use crate::enviroment::{Enviroment, Render};
+use crate::error::{Error, Result};
+
+use std::fmt::Debug;
+use std::io::{BufRead, Write};
+
+/// Play `actions` in `env` from a reset and render it after the reset and after every step.
+/// Fails with `Error::InvalidAction` if the enviroment doesn't accept one of the actions.
+pub fn frames<E>(env: &mut E, actions: &[E::Action]) -> Result<Vec<String>>
+where
+    E: Enviroment + Render,
+    E::Action: Clone + Debug,
+    E::Status: Debug,
+{
+    env.reset();
+    let mut frames = vec![env.render()];
+    for (i, action) in actions.iter().enumerate() {
+        if !env.validate(action.clone()) {
+            return Err(Error::InvalidAction(i));
+        }
+        let (status, _) = env.step(action.clone());
+        frames.push(format!("{}\n{:?}, {:?}", env.render(), action, status));
+    }
+    Ok(frames)
+}
+
+/// Show the frames of a replay one at a time, reading commands from `input` until it ends or the user quits.
+/// Enter or `n` moves forward, `b` back, a number jumps to that step and `q` quits.
+pub fn replay<E, R, W>(
+    env: &mut E,
+    actions: &[E::Action],
+    mut input: R,
+    mut output: W,
+) -> Result<()>
+where
+    E: Enviroment + Render,
+    E::Action: Clone + Debug,
+    E::Status: Debug,
+    R: BufRead,
+    W: Write,
+{
+    let frames = frames(env, actions)?;
+    let last = frames.len() - 1;
+    let mut step = 0;
+    loop {
+        writeln!(output, "{}", frames[step])?;
+        write!(
+            output,
+            "step {}/{} [enter/n: next, b: back, <number>: jump, q: quit] ",
+            step, last
+        )?;
+        output.flush()?;
+
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        match line.trim() {
+            "" | "n" => step = (step + 1).min(last),
+            "b" => step = step.saturating_sub(1),
+            "q" => return Ok(()),
+            other => match other.parse::<usize>() {
+                Ok(target) => step = target.min(last),
+                Err(_) => writeln!(output, "Unknown command '{}'", other)?,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enviroment::discrete::TaggedDiscrete;
+    use crate::games::TicTacToe;
+
+    fn actions(cells: &[u32]) -> Vec<TaggedDiscrete> {
+        cells
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| TaggedDiscrete {
+                action: *cell,
+                player: (i % 2) as u32,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_step_is_rendered() {
+        let frames = frames(&mut TicTacToe::new(), &actions(&[4, 0])).expect("A valid game");
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0], " | | \n-+-+-\n | | \n-+-+-\n | | \n");
+        assert!(frames[2].starts_with("O| | \n-+-+-\n |X| \n"));
+    }
+
+    #[test]
+    fn invalid_actions_stop_the_replay() {
+        match frames(&mut TicTacToe::new(), &actions(&[4, 4])) {
+            Err(Error::InvalidAction(1)) => (),
+            _ => panic!("Expected the second action to be rejected"),
+        }
+    }
+
+    #[test]
+    fn commands_move_through_the_steps() {
+        let mut output = Vec::new();
+        let input = "n\nb\n9\nx\nq\nn\n".as_bytes();
+        replay(
+            &mut TicTacToe::new(),
+            &actions(&[4, 0, 8]),
+            input,
+            &mut output,
+        )
+        .expect("A valid game");
+        let output = String::from_utf8(output).expect("The frames are text");
+        let steps = output
+            .lines()
+            .filter_map(|line| line.split_whitespace().nth(1))
+            .filter(|step| step.contains('/'))
+            .collect::<Vec<_>>();
+        assert_eq!(steps, vec!["0/3", "1/3", "0/3", "3/3", "3/3"]);
+        assert!(output.contains("Unknown command 'x'"));
+    }
+}
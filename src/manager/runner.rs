@@ -0,0 +1,191 @@
+use super::data_collector::DataCollector;
+use crate::agent::{Agent, AgentBuilder};
+use crate::enviroment::{Enviroment, IsTerminal, Validate};
+use crate::error::{self, Result};
+
+/// Runs a single agent in an enviroment, like a control task.
+/// Unlike `Manager` it doesn't need any multiplayer traits and the agent learns from every step as soon as it's taken.
+pub struct Runner<A>
+where
+    A: Agent,
+    A::Env: Enviroment,
+    <A::Env as Validate>::Action: Clone,
+    <A::Env as Enviroment>::Status: IsTerminal,
+{
+    agent: A,
+    data: A::Data,
+    env: A::Env,
+    max_steps: Option<usize>,
+    eval: bool,
+}
+
+impl<A> Runner<A>
+where
+    A: Agent,
+    A::Env: Enviroment,
+    <A::Env as Validate>::Action: Clone,
+    <A::Env as Enviroment>::Status: IsTerminal,
+{
+    pub fn new<B>(agent: B, mut env: A::Env) -> Result<Self>
+    where
+        B: AgentBuilder<A::Env, Agent = A, Data = A::Data>,
+    {
+        let (agent, data) = agent.build(&mut env)?;
+        Ok(Self {
+            agent,
+            data,
+            env,
+            max_steps: None,
+            eval: false,
+        })
+    }
+
+    /// Cut episodes off after `steps` steps, for enviroments which may never terminate
+    pub fn max_steps(mut self, steps: usize) -> Self {
+        self.max_steps.replace(steps);
+        self
+    }
+
+    pub fn episode(&mut self) -> Run<<A::Env as Enviroment>::Status> {
+        self.env.reset();
+        if !self.eval {
+            self.data.begin_episode(&self.env);
+        }
+
+        let mut reward = 0.;
+        let mut steps = 0;
+        loop {
+            let act = self.agent.action(&self.env, &self.data);
+            let (status, step) = self.env.step(act.clone());
+            reward += step;
+            steps += 1;
+            if !self.eval {
+                self.data.push_result(&self.env, act, step);
+                self.agent.update(&self.data);
+            }
+
+            let terminal = status.is_terminal();
+            let truncated = !terminal && self.max_steps.is_some_and(|max| steps >= max);
+            if terminal || truncated {
+                if !self.eval {
+                    // a truncated episode has no terminal state, so its data is left open to be bootstrapped
+                    if terminal {
+                        self.data.end_episode(&self.env);
+                    }
+                    self.agent.end_episode(&self.data);
+                }
+                return Run {
+                    status,
+                    reward,
+                    steps,
+                    truncated,
+                };
+            }
+        }
+    }
+
+    /// Play `episodes` episodes in evaluation mode and return the mean reward
+    pub fn evaluate(&mut self, episodes: usize) -> Result<f32> {
+        error::ensure(episodes > 0, "episodes", "must be at least 1")?;
+        self.set_eval(true);
+        let total = (0..episodes).map(|_| self.episode().reward).sum::<f32>();
+        self.set_eval(false);
+        Ok(total / episodes as f32)
+    }
+
+    /// Switch evaluation mode, in which the agent doesn't collect data or learn
+    pub fn set_eval(&mut self, eval: bool) {
+        self.eval = eval;
+        self.agent.set_eval(eval);
+    }
+
+    pub fn agent(&self) -> &A {
+        &self.agent
+    }
+
+    pub fn agent_mut(&mut self) -> &mut A {
+        &mut self.agent
+    }
+
+    pub fn data(&self) -> &A::Data {
+        &self.data
+    }
+
+    pub fn env(&self) -> &A::Env {
+        &self.env
+    }
+}
+
+/// The result of a single episode of a `Runner`
+#[derive(Clone, Debug)]
+pub struct Run<S> {
+    pub status: S,
+    /// The total reward received by the agent
+    pub reward: f32,
+    pub steps: usize,
+    /// Whether the episode was cut off by `max_steps` before reaching a terminal state
+    pub truncated: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enviroment::EnvBuilder;
+    use crate::error::Error;
+    use crate::testing::doubles::{Entry, Log, Script, ScriptBuilder, TallyBuilder};
+
+    fn runner(len: usize, actions: Vec<u32>, log: &Log) -> Runner<Script> {
+        let script = ScriptBuilder {
+            actions,
+            log: log.clone(),
+        };
+        Runner::new(script, TallyBuilder::new(len).build()).expect("A script fits the enviroment")
+    }
+
+    #[test]
+    fn every_step_is_recorded() {
+        let log = Log::default();
+        let run = runner(3, vec![1, 0], &log).episode();
+        assert_eq!((run.reward, run.steps, run.truncated), (2., 3, false));
+        assert_eq!(
+            log.entries(),
+            vec![
+                Entry::Begin(vec![1., 0.]),
+                Entry::Result(vec![1., 1.], 1, 1.),
+                Entry::Result(vec![1., 2.], 0, 0.),
+                Entry::Result(vec![1., 3.], 1, 1.),
+                Entry::End,
+            ]
+        );
+    }
+
+    #[test]
+    fn truncated_episodes_are_left_open() {
+        let log = Log::default();
+        let mut runner = runner(5, vec![1], &log).max_steps(2);
+        let run = runner.episode();
+        assert_eq!((run.reward, run.steps, run.truncated), (2., 2, true));
+        assert_eq!(
+            log.entries().last(),
+            Some(&Entry::Result(vec![1., 2.], 1, 1.))
+        );
+
+        // the next episode starts over from a reset enviroment
+        runner.episode();
+        assert_eq!(log.entries()[3], Entry::Begin(vec![1., 0.]));
+    }
+
+    #[test]
+    fn evaluation_averages_without_recording() {
+        let log = Log::default();
+        let mut runner = runner(3, vec![1], &log);
+        assert_eq!(runner.evaluate(4).expect("Evaluated some episodes"), 3.);
+        assert!(log.entries().is_empty());
+        match runner.evaluate(0) {
+            Err(Error::InvalidParameter {
+                name: "episodes", ..
+            }) => (),
+            _ => panic!("Expected an evaluation without episodes to be rejected"),
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use std::collections::VecDeque;
+
+use random_fast_rng::FastRng;
+
+use super::{AgentWrapper, Manager, ManagerBuilder, Summary};
+use crate::enviroment::{AssignRewards, EnvBuilder, Enviroment, IsTerminal, PlayerRange};
+use crate::error::{self, Result};
+use crate::misc::random;
+
+/// How opponents are picked from the pool of snapshots
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sampling {
+    Uniform,
+    /// The chance of picking a snapshot grows linearly with how recent it is
+    Recency,
+}
+
+/// Trains one agent against frozen copies of itself.
+/// A snapshot of the learner is added to the pool every `every` updates, the oldest ones are dropped once there are more than `size`,
+/// and all the other seats are filled with distinct snapshots sampled before each episode.
+/// Every agent plays as the player with the same index as its seat, like the tokens handed out by `ManagerBuilder`.
+pub struct SelfPlay<E>
+where
+    E: Enviroment,
+    E::Action: Clone,
+    E::Status: IsTerminal,
+    E: AssignRewards,
+{
+    manager: Manager<E>,
+    players: usize,
+    learner: usize,
+    /// Snapshots which aren't seated, ordered from the oldest
+    pool: VecDeque<(usize, Box<dyn AgentWrapper<Env = E>>)>,
+    /// Ids of the snapshots currently seated, in seat order
+    seated: Vec<usize>,
+
+    every: usize,
+    size: usize,
+    sampling: Sampling,
+
+    next_id: usize,
+    last: usize,
+    rng: FastRng,
+}
+
+impl<E> SelfPlay<E>
+where
+    E: Enviroment,
+    E::Action: Clone,
+    E::Status: IsTerminal,
+    E: AssignRewards,
+{
+    /// `builder` has to hold only the learner, added with `ManagerBuilder::add_learner`, which plays in seat `learner` of `players`.
+    /// The opponents are sampled with a seed from the builder's master seed, if it has one.
+    pub fn new<B>(
+        builder: ManagerBuilder<B>,
+        players: usize,
+        learner: usize,
+        every: usize,
+        size: usize,
+        sampling: Sampling,
+    ) -> Result<Self>
+    where
+        B: EnvBuilder<Output = E> + PlayerRange,
+    {
+        ManagerBuilder::<B>::check_players(players)?;
+        error::ensure(
+            builder.agents.len() == 1,
+            "builder",
+            "has to hold only the learner",
+        )?;
+        error::ensure(
+            builder.agents[0].snapshot().is_some(),
+            "builder",
+            "the learner has to be added with `add_learner`",
+        )?;
+        error::ensure(learner < players, "learner", "seat index out of bounds")?;
+        error::ensure(every > 0, "every", "must be at least 1")?;
+        error::ensure(
+            size + 1 >= players,
+            "size",
+            "the pool has to be large enough to fill all the seats",
+        )?;
+
+        let mut manager = builder.finish();
+        // unseeded managers keep a fixed sampling of opponents
+        let seed = manager
+            .fork_seeder()
+            .map_or(0, |mut seeder| seeder.next_seed());
+        let learner_agent = manager
+            .replace_agents(Vec::new())
+            .pop()
+            .expect("The builder holds the learner");
+        let mut self_play = Self {
+            manager,
+            players,
+            learner,
+            pool: VecDeque::new(),
+            seated: Vec::new(),
+            every,
+            size,
+            sampling,
+            next_id: 0,
+            last: learner_agent.updates(),
+            rng: FastRng::seed(seed, 0),
+        };
+        for _ in 1..players {
+            self_play.push_snapshot(&*learner_agent);
+        }
+        self_play.seat(learner_agent);
+        Ok(self_play)
+    }
+
+    pub fn episode(&mut self) -> Summary<E::Status, E::Action> {
+        let mut agents = self.manager.replace_agents(Vec::new());
+        let learner = agents.remove(self.learner);
+
+        let seated = std::mem::take(&mut self.seated);
+        for (id, agent) in seated.into_iter().zip(agents) {
+            let pos = self
+                .pool
+                .iter()
+                .position(|(x, _)| *x > id)
+                .unwrap_or(self.pool.len());
+            self.pool.insert(pos, (id, agent));
+        }
+
+        if learner.updates() >= self.last + self.every {
+            self.last = learner.updates();
+            self.push_snapshot(&*learner);
+            while self.pool.len() > self.size {
+                self.pool.pop_front();
+            }
+        }
+
+        self.seat(learner);
+        self.manager.episode()
+    }
+
+    /// The number of snapshots in the pool, including the seated ones
+    pub fn pool_len(&self) -> usize {
+        self.pool.len() + self.seated.len()
+    }
+
+    pub fn manager(&mut self) -> &mut Manager<E> {
+        &mut self.manager
+    }
+
+    fn push_snapshot(&mut self, learner: &dyn AgentWrapper<Env = E>) {
+        let snapshot = learner
+            .snapshot()
+            .expect("The learner has to be added with `add_learner`");
+        self.pool.push_back((self.next_id, snapshot));
+        self.next_id += 1;
+    }
+
+    /// Sample opponents without replacement and seat them around the learner
+    fn seat(&mut self, learner: Box<dyn AgentWrapper<Env = E>>) {
+        let mut agents = Vec::with_capacity(self.players);
+        for _ in 1..self.players {
+            let weights = (0..self.pool.len())
+                .map(|i| match self.sampling {
+                    Sampling::Uniform => 1.,
+                    Sampling::Recency => (i + 1) as f32,
+                })
+                .collect::<Vec<_>>();
+            let idx = random::sample(&mut self.rng, &weights);
+            let (id, agent) = self.pool.remove(idx).expect("Pool index out of bounds");
+            self.seated.push(id);
+            agents.push(agent);
+        }
+        agents.insert(self.learner, learner);
+        // snapshots are copies of the learner, so they have to be moved to their own seats
+        for (seat, agent) in agents.iter_mut().enumerate() {
+            agent.set_player(seat as u32);
+        }
+        self.manager.replace_agents(agents);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::testing::doubles::{Entry, Log, ScriptBuilder, TallyBuilder};
+
+    fn builder(log: &Log) -> ManagerBuilder<TallyBuilder> {
+        let mut builder = ManagerBuilder::new(TallyBuilder::new(3));
+        builder
+            .add_learner(ScriptBuilder {
+                actions: vec![1],
+                log: log.clone(),
+            })
+            .expect("The script takes the first player");
+        builder
+    }
+
+    #[test]
+    fn snapshots_fill_the_other_seats() {
+        let log = Log::default();
+        let mut self_play = SelfPlay::new(builder(&log), 3, 1, 10, 2, Sampling::Uniform)
+            .expect("Valid self-play parameters");
+        assert_eq!(self_play.pool_len(), 2);
+
+        let summary = self_play.episode();
+        let players = summary.actions.iter().map(|a| a.player).collect::<Vec<_>>();
+        assert_eq!(players, vec![0, 1, 2]);
+        // only the learner records its experience, as the player of its own seat
+        assert_eq!(
+            log.entries(),
+            vec![
+                Entry::Begin(vec![1., 1.]),
+                Entry::Result(vec![1., 3.], 1, 2.),
+                Entry::End,
+            ]
+        );
+    }
+
+    #[test]
+    fn the_pool_keeps_the_latest_snapshots() {
+        let log = Log::default();
+        let mut self_play = SelfPlay::new(builder(&log), 2, 0, 1, 3, Sampling::Recency)
+            .expect("Valid self-play parameters");
+        assert_eq!(self_play.pool_len(), 1);
+        self_play.episode();
+        assert_eq!(self_play.pool_len(), 1);
+        self_play.episode();
+        assert_eq!(self_play.pool_len(), 2);
+        for _ in 0..5 {
+            self_play.episode();
+        }
+        assert_eq!(self_play.pool_len(), 3);
+        assert_eq!(self_play.manager().agents().len(), 2);
+    }
+
+    #[test]
+    fn invalid_parameters_are_rejected() {
+        let log = Log::default();
+        match SelfPlay::new(builder(&log), 0, 0, 1, 2, Sampling::Uniform) {
+            Err(Error::PlayerCount { count: 0, .. }) => (),
+            _ => panic!("Expected a player count error"),
+        }
+        match SelfPlay::new(builder(&log), 3, 3, 1, 2, Sampling::Uniform) {
+            Err(Error::InvalidParameter {
+                name: "learner", ..
+            }) => (),
+            _ => panic!("Expected the learner seat to be rejected"),
+        }
+        match SelfPlay::new(builder(&log), 3, 0, 1, 1, Sampling::Uniform) {
+            Err(Error::InvalidParameter { name: "size", .. }) => (),
+            _ => panic!("Expected the pool size to be rejected"),
+        }
+
+        let mut opponent = builder(&log);
+        opponent
+            .add_agent(ScriptBuilder {
+                actions: vec![0],
+                log: log.clone(),
+            })
+            .expect("The script takes the second player");
+        match SelfPlay::new(opponent, 2, 0, 1, 1, Sampling::Uniform) {
+            Err(Error::InvalidParameter {
+                name: "builder", ..
+            }) => (),
+            _ => panic!("Expected a builder with opponents to be rejected"),
+        }
+    }
+}
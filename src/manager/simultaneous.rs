@@ -0,0 +1,160 @@
+use super::{AgentWrapper, Renderer, Summary};
+use crate::enviroment::{IsTerminal, Simultaneous};
+
+/// Runs episodes of enviroments in which all agents act at the same time.
+/// Every step each agent picks an action for the same state and the actions are applied together.
+pub struct SimultaneousManager<E>
+where
+    E: Simultaneous,
+    E::Action: Clone,
+    E::Status: IsTerminal,
+{
+    agents: Vec<Box<dyn AgentWrapper<Env = E>>>,
+    env: E,
+    render: Option<Renderer<E>>,
+}
+
+impl<E> SimultaneousManager<E>
+where
+    E: Simultaneous,
+    E::Action: Clone,
+    E::Status: IsTerminal,
+{
+    pub(crate) fn new(
+        agents: Vec<Box<dyn AgentWrapper<Env = E>>>,
+        env: E,
+        render: Option<Renderer<E>>,
+    ) -> Self {
+        Self {
+            agents,
+            env,
+            render,
+        }
+    }
+
+    pub fn episode(&mut self) -> Summary<E::Status, E::Action> {
+        self.env.reset();
+        for agent in self.agents.iter_mut() {
+            agent.begin_episode();
+        }
+        let mut rewards = vec![0.; self.agents.len()];
+        let mut frames = Vec::new();
+        let mut history = Vec::new();
+        loop {
+            let env = &self.env;
+            let actions = self
+                .agents
+                .iter_mut()
+                .map(|agent| agent.action(env))
+                .collect::<Vec<_>>();
+            history.extend(actions.iter().cloned());
+            let (status, step) = self.env.step_joint(actions);
+            if let Some(renderer) = &self.render {
+                renderer.frame(&self.env, &mut frames);
+            }
+            assert_eq!(
+                step.len(),
+                self.agents.len(),
+                "Expected a reward for every agent"
+            );
+
+            for (i, agent) in self.agents.iter_mut().enumerate() {
+                agent.push_result(step[i]);
+                rewards[i] += step[i];
+            }
+
+            if status.is_terminal() {
+                for agent in self.agents.iter_mut() {
+                    agent.end_episode(&self.env, 0.);
+                }
+                return Summary {
+                    status,
+                    rewards,
+                    seats: (0..self.agents.len()).collect(),
+                    frames,
+                    actions: history,
+                };
+            }
+        }
+    }
+
+    pub fn agents(&self) -> &[Box<dyn AgentWrapper<Env = E>>] {
+        &self.agents
+    }
+
+    /// Swap out the agents, which lets them change between episodes without rebuilding the enviroment
+    pub fn replace_agents(
+        &mut self,
+        agents: Vec<Box<dyn AgentWrapper<Env = E>>>,
+    ) -> Vec<Box<dyn AgentWrapper<Env = E>>> {
+        std::mem::replace(&mut self.agents, agents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Error;
+    use crate::manager::ManagerBuilder;
+    use crate::testing::doubles::{Entry, Log, ScriptBuilder, TallyBuilder};
+
+    #[test]
+    fn agents_act_on_the_same_state() {
+        let (first, second) = (Log::default(), Log::default());
+        let mut builder = ManagerBuilder::new(TallyBuilder::new(4));
+        for (action, log) in [(1, &first), (0, &second)].iter() {
+            let actions = vec![*action];
+            let log = (*log).clone();
+            builder
+                .add_agent(ScriptBuilder { actions, log })
+                .expect("The agents fit the enviroment");
+        }
+        let mut manager = builder
+            .build_simultaneous()
+            .expect("The agents fit the enviroment");
+        let summary = manager.episode();
+
+        assert_eq!(summary.rewards, vec![2., 0.]);
+        assert_eq!(summary.seats, vec![0, 1]);
+        let players = summary.actions.iter().map(|a| a.player).collect::<Vec<_>>();
+        assert_eq!(players, vec![0, 1, 0, 1]);
+        for (action, log) in [(1, &first), (0, &second)].iter() {
+            let reward = *action as f32;
+            assert_eq!(
+                log.entries(),
+                vec![
+                    Entry::Begin(vec![1., 0.]),
+                    Entry::Result(vec![1., 2.], *action, reward),
+                    Entry::Result(vec![1., 4.], *action, reward),
+                    Entry::End,
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn episodes_start_over() {
+        let log = Log::default();
+        let mut builder = ManagerBuilder::new(TallyBuilder::new(2));
+        builder
+            .add_agent(ScriptBuilder {
+                actions: vec![1, 0],
+                log: log.clone(),
+            })
+            .expect("A single agent fits the enviroment");
+        let mut manager = builder
+            .build_simultaneous()
+            .expect("A single agent fits the enviroment");
+        assert_eq!(manager.episode().rewards, vec![1.]);
+        assert_eq!(manager.episode().rewards, vec![1.]);
+        assert_eq!(log.entries().len(), 8);
+    }
+
+    #[test]
+    fn the_number_of_agents_is_checked() {
+        let builder = ManagerBuilder::new(TallyBuilder::new(2));
+        match builder.build_simultaneous() {
+            Err(Error::PlayerCount { count: 0, .. }) => (),
+            _ => panic!("Expected a player count error"),
+        }
+    }
+}
@@ -0,0 +1,566 @@
+use super::{AgentWrapper, Evaluation, Manager, Outcome, Summary};
+use crate::enviroment::{AssignRewards, Enviroment, IsTerminal};
+use crate::error::{Error, Result};
+
+use std::cmp::Ordering;
+
+type OnGame<S, A> = Box<dyn FnMut([usize; 2], &Summary<S, A>)>;
+
+/// Settings of the TrueSkill rating system, the defaults are the ones from the original paper
+#[derive(Clone, Copy, Debug)]
+pub struct TrueSkillConfig {
+    /// Initial mean skill
+    pub mu: f32,
+    /// Initial uncertainty
+    pub sigma: f32,
+    /// The variance of a single game's performance around the skill
+    pub beta: f32,
+    /// Uncertainty added before every game, which lets the ratings follow agents which keep improving
+    pub tau: f32,
+    /// Games with a performance difference below this are expected to be drawn
+    pub draw_margin: f32,
+}
+
+impl Default for TrueSkillConfig {
+    fn default() -> Self {
+        Self {
+            mu: 25.,
+            sigma: 25. / 3.,
+            beta: 25. / 6.,
+            tau: 25. / 300.,
+            // a draw probability of 10%
+            draw_margin: 0.74,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TrueSkill {
+    pub mu: f32,
+    pub sigma: f32,
+}
+
+impl TrueSkill {
+    /// The skill the agent has with high certainty, used for ranking
+    pub fn conservative(&self) -> f32 {
+        self.mu - 3. * self.sigma
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Rating {
+    pub elo: f32,
+    pub trueskill: Option<TrueSkill>,
+}
+
+/// The results of a match from the point of view of `first`
+#[derive(Clone, Copy, Debug)]
+pub struct Match {
+    pub first: usize,
+    pub second: usize,
+    pub result: Evaluation,
+}
+
+/// Ranks agents by playing matches between pairs of them in evaluation mode.
+/// The enviroment has to accept two players and the agents switch seats after every game.
+/// Agents play as the player of their seat, so they don't have to be built for a particular one.
+pub struct Tournament<E>
+where
+    E: Enviroment,
+    E::Action: Clone,
+    E::Status: IsTerminal,
+    E: AssignRewards,
+{
+    manager: Manager<E>,
+    agents: Vec<Option<Box<dyn AgentWrapper<Env = E>>>>,
+    ratings: Vec<Rating>,
+    history: Vec<Match>,
+
+    k: f32,
+    trueskill: Option<TrueSkillConfig>,
+    on_game: Option<OnGame<E::Status, E::Action>>,
+}
+
+impl<E> Tournament<E>
+where
+    E: Enviroment,
+    E::Action: Clone,
+    E::Status: IsTerminal,
+    E: AssignRewards,
+{
+    /// The manager only decides the enviroment and its two seats, its own agents are dropped and replaced by `agents`.
+    /// Every agent starts with the Elo rating of 1500.
+    /// Fails with `Error::PlayerCount` unless the manager has exactly two seats
+    pub fn new(
+        mut manager: Manager<E>,
+        agents: Vec<Box<dyn AgentWrapper<Env = E>>>,
+    ) -> Result<Self> {
+        let count = manager.agents.len();
+        if count != 2 {
+            return Err(Error::PlayerCount {
+                count,
+                min: 2,
+                max: Some(2),
+            });
+        }
+        manager.replace_agents(Vec::new());
+
+        let agents = agents
+            .into_iter()
+            .map(|mut agent| {
+                agent.set_eval(true);
+                Some(agent)
+            })
+            .collect::<Vec<_>>();
+        let ratings = vec![
+            Rating {
+                elo: 1500.,
+                trueskill: None,
+            };
+            agents.len()
+        ];
+        Ok(Self {
+            manager,
+            agents,
+            ratings,
+            history: Vec::new(),
+            k: 32.,
+            trueskill: None,
+            on_game: None,
+        })
+    }
+
+    /// How much a single game can change the Elo rating
+    pub fn k_factor(mut self, k: f32) -> Self {
+        self.k = k;
+        self
+    }
+
+    /// Keep TrueSkill ratings alongside the Elo ratings and rank the agents by them
+    pub fn trueskill(mut self, config: TrueSkillConfig) -> Self {
+        for rating in self.ratings.iter_mut() {
+            rating.trueskill.replace(TrueSkill {
+                mu: config.mu,
+                sigma: config.sigma,
+            });
+        }
+        self.trueskill.replace(config);
+        self
+    }
+
+    /// Call `f` after every game with the indices of the agents in seat order and the summary of the game,
+    /// for example to keep the games with `Game::write` and watch them in the `replay` binary
+    pub fn on_game<F>(mut self, f: F) -> Self
+    where
+        F: FnMut([usize; 2], &Summary<E::Status, E::Action>) + 'static,
+    {
+        self.on_game.replace(Box::new(f));
+        self
+    }
+
+    /// Play `games` games between two agents and update their ratings after each one.
+    /// Fails without playing if the agents are the same, don't exist or are missing their rating.
+    pub fn play_match(&mut self, first: usize, second: usize, games: usize) -> Result<Match> {
+        self.check_match(first, second)?;
+        let a = self.agents[first]
+            .take()
+            .ok_or(Error::MissingAgent(first))?;
+        let b = self.agents[second]
+            .take()
+            .ok_or(Error::MissingAgent(second))?;
+        self.manager.replace_agents(vec![a, b]);
+
+        let result = self.play_games(first, second, games);
+        let mut agents = self.manager.replace_agents(Vec::new());
+        self.agents[second] = agents.pop();
+        self.agents[first] = agents.pop();
+
+        let record = Match {
+            first,
+            second,
+            result: result?,
+        };
+        self.history.push(record);
+        Ok(record)
+    }
+
+    /// Play a match between every pair of agents
+    pub fn round_robin(&mut self, games: usize) -> Result<()> {
+        for first in 0..self.agents.len() {
+            for second in first + 1..self.agents.len() {
+                self.play_match(first, second, games)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Play `rounds` rounds in which agents are paired with the closest ranked agent they haven't met yet.
+    /// With an odd number of agents the lowest ranked one sits out each round.
+    pub fn swiss(&mut self, rounds: usize, games: usize) -> Result<()> {
+        for _ in 0..rounds {
+            let mut unpaired = self.standings();
+            if unpaired.len() % 2 == 1 {
+                unpaired.pop();
+            }
+
+            let mut pairs = Vec::new();
+            while !unpaired.is_empty() {
+                let first = unpaired.remove(0);
+                let opponent = unpaired
+                    .iter()
+                    .position(|second| !self.have_met(first, *second))
+                    .unwrap_or(0);
+                pairs.push((first, unpaired.remove(opponent)));
+            }
+
+            for (first, second) in pairs {
+                self.play_match(first, second, games)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Indices of the agents ordered from the best.
+    /// Uses the conservative TrueSkill estimate if enabled and the Elo rating otherwise.
+    /// Agents whose rating isn't a number are ranked last.
+    pub fn standings(&self) -> Vec<usize> {
+        let score = |i: usize| {
+            let score = match self.ratings[i].trueskill {
+                Some(trueskill) => trueskill.conservative(),
+                None => self.ratings[i].elo,
+            };
+            if score.is_nan() {
+                f32::NEG_INFINITY
+            } else {
+                score
+            }
+        };
+        let mut standings = (0..self.ratings.len()).collect::<Vec<_>>();
+        standings.sort_by(|a, b| score(*b).partial_cmp(&score(*a)).unwrap_or(Ordering::Equal));
+        standings
+    }
+
+    pub fn ratings(&self) -> &[Rating] {
+        &self.ratings
+    }
+
+    pub fn history(&self) -> &[Match] {
+        &self.history
+    }
+
+    /// Returns the agents with evaluation mode turned off.
+    /// Fails with `Error::MissingAgent` if a match didn't finish.
+    pub fn into_agents(self) -> Result<Vec<Box<dyn AgentWrapper<Env = E>>>> {
+        self.agents
+            .into_iter()
+            .enumerate()
+            .map(|(i, agent)| {
+                let mut agent = agent.ok_or(Error::MissingAgent(i))?;
+                agent.set_eval(false);
+                Ok(agent)
+            })
+            .collect()
+    }
+
+    fn check_match(&self, first: usize, second: usize) -> Result<()> {
+        if first == second {
+            return Err(Error::SelfMatch(first));
+        }
+        let count = self.agents.len();
+        for &index in [first, second].iter() {
+            if index >= count {
+                return Err(Error::AgentIndex { index, count });
+            }
+            if self.agents[index].is_none() {
+                return Err(Error::MissingAgent(index));
+            }
+            if self.trueskill.is_some() && self.ratings[index].trueskill.is_none() {
+                return Err(Error::MissingRating(index));
+            }
+        }
+        Ok(())
+    }
+
+    /// Play the games of a match between the agents seated in the manager
+    fn play_games(&mut self, first: usize, second: usize, games: usize) -> Result<Evaluation> {
+        let mut result = Evaluation::default();
+        for game in 0..games {
+            let swapped = game % 2 == 1;
+            if swapped {
+                self.manager.agents.swap(0, 1);
+            }
+            // the agents may come from different managers, so their players have to match their seats
+            for (seat, agent) in self.manager.agents.iter_mut().enumerate() {
+                agent.set_player(seat as u32);
+            }
+            let summary = self.manager.episode();
+            if let Some(on_game) = &mut self.on_game {
+                let seats = if swapped {
+                    [second, first]
+                } else {
+                    [first, second]
+                };
+                on_game(seats, &summary);
+            }
+            let outcome = summary.outcome(swapped as usize);
+            if swapped {
+                self.manager.agents.swap(0, 1);
+            }
+
+            match outcome {
+                Outcome::Win => result.wins += 1,
+                Outcome::Draw => result.draws += 1,
+                Outcome::Loss => result.losses += 1,
+            }
+            self.rate(first, second, outcome)?;
+        }
+        Ok(result)
+    }
+
+    fn have_met(&self, a: usize, b: usize) -> bool {
+        self.history.iter().any(|record| {
+            (record.first == a && record.second == b) || (record.first == b && record.second == a)
+        })
+    }
+
+    fn rate(&mut self, first: usize, second: usize, outcome: Outcome) -> Result<()> {
+        let score = match outcome {
+            Outcome::Win => 1.,
+            Outcome::Draw => 0.5,
+            Outcome::Loss => 0.,
+        };
+        let change = elo_change(
+            self.k,
+            self.ratings[first].elo,
+            self.ratings[second].elo,
+            score,
+        );
+        self.ratings[first].elo += change;
+        self.ratings[second].elo -= change;
+
+        if let Some(config) = self.trueskill {
+            let a = self.ratings[first]
+                .trueskill
+                .ok_or(Error::MissingRating(first))?;
+            let b = self.ratings[second]
+                .trueskill
+                .ok_or(Error::MissingRating(second))?;
+            let (a, b) = match outcome {
+                Outcome::Win => trueskill_update(&config, a, b, false),
+                Outcome::Draw => trueskill_update(&config, a, b, true),
+                Outcome::Loss => {
+                    let (b, a) = trueskill_update(&config, b, a, false);
+                    (a, b)
+                }
+            };
+            self.ratings[first].trueskill.replace(a);
+            self.ratings[second].trueskill.replace(b);
+        }
+        Ok(())
+    }
+}
+
+/// How much the Elo rating of `a` changes after scoring `score` against `b`, the rating of `b` changes by the opposite
+fn elo_change(k: f32, a: f32, b: f32, score: f32) -> f32 {
+    let expected = 1. / (1. + 10f32.powf((b - a) / 400.));
+    k * (score - expected)
+}
+
+/// Update the ratings after `winner` beat `loser`, or after they drew
+fn trueskill_update(
+    config: &TrueSkillConfig,
+    winner: TrueSkill,
+    loser: TrueSkill,
+    draw: bool,
+) -> (TrueSkill, TrueSkill) {
+    let var_w = winner.sigma.powi(2) + config.tau.powi(2);
+    let var_l = loser.sigma.powi(2) + config.tau.powi(2);
+    let c = (2. * config.beta.powi(2) + var_w + var_l).sqrt();
+    let t = (winner.mu - loser.mu) / c;
+    let e = config.draw_margin / c;
+
+    let (v, w) = if draw {
+        let denom = (cdf(e - t) - cdf(-e - t)).max(f32::EPSILON);
+        let v = (pdf(-e - t) - pdf(e - t)) / denom;
+        let w = v * v + ((e - t) * pdf(e - t) + (e + t) * pdf(e + t)) / denom;
+        (v, w)
+    } else {
+        let v = pdf(t - e) / cdf(t - e).max(f32::EPSILON);
+        (v, v * (v + t - e))
+    };
+
+    let update = |var: f32, mu: f32, sign: f32| TrueSkill {
+        mu: mu + sign * var / c * v,
+        sigma: (var * (1. - var / (c * c) * w).max(f32::EPSILON)).sqrt(),
+    };
+    (update(var_w, winner.mu, 1.), update(var_l, loser.mu, -1.))
+}
+
+/// Density of the standard normal distribution
+fn pdf(x: f32) -> f32 {
+    (-x * x / 2.).exp() / (2. * std::f32::consts::PI).sqrt()
+}
+
+/// Cumulative distribution of the standard normal distribution
+fn cdf(x: f32) -> f32 {
+    (1. + erf(x / std::f32::consts::SQRT_2)) / 2.
+}
+
+fn erf(x: f32) -> f32 {
+    // Abramowitz and Stegun 7.1.26
+    let sign = x.signum();
+    let x = x.abs();
+    let t = 1. / (1. + 0.327_591_1 * x);
+    let poly = t
+        * (0.254_829_6
+            + t * (-0.284_496_7 + t * (1.421_413_7 + t * (-1.453_152 + t * 1.061_405_4))));
+    sign * (1. - poly * (-x * x).exp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::AgentBuilder;
+    use crate::manager::{ManagerBuilder, Wrapper};
+    use crate::testing::doubles::{Log, ScriptBuilder, Tally, TallyBuilder};
+
+    fn assert_close(found: f32, expected: f32, tolerance: f32) {
+        assert!(
+            (found - expected).abs() < tolerance,
+            "expected {}, found {}",
+            expected,
+            found
+        );
+    }
+
+    fn initial(config: &TrueSkillConfig) -> TrueSkill {
+        TrueSkill {
+            mu: config.mu,
+            sigma: config.sigma,
+        }
+    }
+
+    #[test]
+    fn erf_matches_known_values() {
+        assert_close(erf(0.), 0., 1e-6);
+        assert_close(erf(0.5), 0.520_499_9, 1e-6);
+        assert_close(erf(1.), 0.842_700_8, 1e-6);
+        assert_close(erf(-1.), -0.842_700_8, 1e-6);
+        assert_close(erf(3.), 0.999_977_9, 1e-6);
+    }
+
+    #[test]
+    fn normal_distribution() {
+        assert_close(pdf(0.), 0.398_942_3, 1e-6);
+        assert_close(pdf(1.), pdf(-1.), 1e-6);
+        assert_close(cdf(0.), 0.5, 1e-6);
+        assert_close(cdf(1.96), 0.975, 1e-4);
+        assert_close(cdf(-1.96), 0.025, 1e-4);
+    }
+
+    #[test]
+    fn elo_between_equals() {
+        assert_close(elo_change(32., 1500., 1500., 1.), 16., 1e-4);
+        assert_close(elo_change(32., 1500., 1500., 0.5), 0., 1e-4);
+        assert_close(elo_change(32., 1500., 1500., 0.), -16., 1e-4);
+    }
+
+    #[test]
+    fn elo_favours_upsets() {
+        // a 400 point gap means the stronger player is expected to score 10/11
+        assert_close(elo_change(32., 1900., 1500., 1.), 32. / 11., 1e-3);
+        assert_close(elo_change(32., 1500., 1900., 1.), 320. / 11., 1e-3);
+    }
+
+    #[test]
+    fn trueskill_win() {
+        // the reference values of a first game between new players
+        let config = TrueSkillConfig::default();
+        let (winner, loser) = trueskill_update(&config, initial(&config), initial(&config), false);
+        assert_close(winner.mu, 29.396, 0.01);
+        assert_close(winner.sigma, 7.171, 0.01);
+        assert_close(loser.mu, 20.604, 0.01);
+        assert_close(loser.sigma, 7.171, 0.01);
+    }
+
+    #[test]
+    fn trueskill_draw() {
+        let config = TrueSkillConfig::default();
+        let (a, b) = trueskill_update(&config, initial(&config), initial(&config), true);
+        assert_close(a.mu, 25., 0.01);
+        assert_close(b.mu, 25., 0.01);
+        assert_close(a.sigma, 6.458, 0.01);
+        assert_close(b.sigma, 6.458, 0.01);
+    }
+
+    #[test]
+    fn trueskill_draw_pulls_ratings_together() {
+        let config = TrueSkillConfig::default();
+        let strong = TrueSkill { mu: 30., sigma: 4. };
+        let weak = TrueSkill { mu: 20., sigma: 4. };
+        let (strong, weak) = trueskill_update(&config, strong, weak, true);
+        assert!(strong.mu < 30.);
+        assert!(weak.mu > 20.);
+    }
+
+    /// A tournament of agents which always play their action, so higher actions win
+    fn tournament(actions: &[u32]) -> Tournament<Tally> {
+        let script = |action: u32| ScriptBuilder {
+            actions: vec![action],
+            log: Log::default(),
+        };
+        let mut builder = ManagerBuilder::new(TallyBuilder::new(2));
+        for _ in 0..2 {
+            builder
+                .add_agent(script(0))
+                .expect("The agents fit the enviroment");
+        }
+        let manager = builder.build().expect("The agents fit the enviroment");
+
+        let mut env = TallyBuilder::new(2);
+        let agents = actions
+            .iter()
+            .map(|action| {
+                let (agent, log) = script(*action)
+                    .build(&mut env)
+                    .expect("A script always builds");
+                Box::new(Wrapper::new(agent, log)) as Box<dyn AgentWrapper<Env = Tally>>
+            })
+            .collect();
+        Tournament::new(manager, agents).expect("The manager has two seats")
+    }
+
+    #[test]
+    fn winners_gain_rating() {
+        let mut tournament = tournament(&[1, 0, 0]).trueskill(TrueSkillConfig::default());
+        let record = tournament.play_match(0, 1, 4).expect("A valid match");
+        let result = &record.result;
+        assert_eq!((result.wins, result.draws, result.losses), (4, 0, 0));
+        assert!(tournament.ratings()[0].elo > tournament.ratings()[1].elo);
+
+        tournament.round_robin(2).expect("Valid matches");
+        assert_eq!(tournament.history().len(), 4);
+        assert_eq!(tournament.standings()[0], 0);
+        let agents = tournament.into_agents().expect("Every match finished");
+        assert_eq!(agents.len(), 3);
+    }
+
+    #[test]
+    fn invalid_matches_are_rejected() {
+        let mut tournament = tournament(&[1, 0]);
+        match tournament.play_match(1, 1, 2) {
+            Err(Error::SelfMatch(1)) => (),
+            _ => panic!("Expected an agent playing itself to be rejected"),
+        }
+        match tournament.play_match(0, 2, 2) {
+            Err(Error::AgentIndex { index: 2, count: 2 }) => (),
+            _ => panic!("Expected a missing agent to be rejected"),
+        }
+        // no agent is lost to the rejected matches
+        assert!(tournament.history().is_empty());
+        assert_eq!(
+            tournament.into_agents().expect("No match was played").len(),
+            2
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use std::{collections::VecDeque, ops::{Index, IndexMut, Range}};
+use std::ops::{Index, IndexMut, Range};
 
 pub struct CyclicBuffer<D> {
     pub(crate) buffer: Vec<D>,
@@ -17,6 +17,10 @@ impl<D> CyclicBuffer<D> {
         self.buffer.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
     pub fn iter<'a>(&'a self) -> Cycle<'a, D> {
         self.into_iter()
     }
@@ -77,7 +81,59 @@ impl<D> CyclicBuffer<D> {
     }
 }
 
-impl<'a, D> Index<usize> for CyclicBuffer<D> {
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use super::CyclicBuffer;
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize)]
+    struct Borrowed<'a, D> {
+        capacity: usize,
+        idx: usize,
+        buffer: &'a [D],
+    }
+
+    #[derive(Deserialize)]
+    struct Owned<D> {
+        capacity: usize,
+        idx: usize,
+        buffer: Vec<D>,
+    }
+
+    impl<D: Serialize> Serialize for CyclicBuffer<D> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            Borrowed {
+                capacity: self.buffer.capacity(),
+                idx: self.idx,
+                buffer: &self.buffer,
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de, D: Deserialize<'de>> Deserialize<'de> for CyclicBuffer<D> {
+        fn deserialize<De: Deserializer<'de>>(deserializer: De) -> Result<Self, De::Error> {
+            let Owned {
+                capacity,
+                idx,
+                buffer,
+            } = Owned::deserialize(deserializer)?;
+
+            if buffer.len() > capacity {
+                return Err(De::Error::custom("buffer is larger than its capacity"));
+            }
+            if idx != 0 && idx >= buffer.len() {
+                return Err(De::Error::custom("index out of bounds"));
+            }
+
+            let mut vec = Vec::with_capacity(capacity);
+            vec.extend(buffer);
+            Ok(CyclicBuffer { buffer: vec, idx })
+        }
+    }
+}
+
+impl<D> Index<usize> for CyclicBuffer<D> {
     type Output = D;
 
     fn index(&self, index: usize) -> &Self::Output {
@@ -86,7 +142,7 @@ impl<'a, D> Index<usize> for CyclicBuffer<D> {
     }
 }
 
-impl<'a, D> IndexMut<usize> for CyclicBuffer<D> {
+impl<D> IndexMut<usize> for CyclicBuffer<D> {
     fn index_mut(&mut self, index: usize) -> &mut Self::Output {
         assert!(index < self.len());
         let idx = (self.idx + index) % self.len();
@@ -453,3 +509,41 @@ impl<'a, D> IntoIterator for &Slice<'a, D> {
 //         (self.idx + self.slice.len() - self.end - 1) % self.slice.len() + 1
 //     }
 // }
+
+// serde_json only comes with the `record` feature
+#[cfg(all(test, feature = "record"))]
+mod tests {
+    use super::*;
+
+    fn buffer(capacity: usize, values: &[u32]) -> CyclicBuffer<u32> {
+        let mut buffer = CyclicBuffer::new(capacity);
+        for value in values {
+            buffer.push(*value);
+        }
+        buffer
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        for values in [&[][..], &[1, 2], &[1, 2, 3, 4, 5, 6]].iter() {
+            let mut buffer = buffer(4, values);
+            let json = serde_json::to_string(&buffer).expect("Serializing can't fail");
+            let mut restored: CyclicBuffer<u32> =
+                serde_json::from_str(&json).expect("Deserializing a serialized buffer can't fail");
+            assert_eq!(restored.buffer, buffer.buffer);
+            assert_eq!(restored.idx, buffer.idx);
+            assert_eq!(restored.buffer.capacity(), buffer.buffer.capacity());
+            // the restored buffer keeps going like the original
+            assert_eq!(restored.push(7), buffer.push(7));
+            assert_eq!(restored.buffer, buffer.buffer);
+        }
+    }
+
+    #[test]
+    fn serde_rejects_broken_buffers() {
+        let too_long = r#"{"capacity":1,"idx":0,"buffer":[1,2]}"#;
+        assert!(serde_json::from_str::<CyclicBuffer<u32>>(too_long).is_err());
+        let bad_idx = r#"{"capacity":3,"idx":2,"buffer":[1,2]}"#;
+        assert!(serde_json::from_str::<CyclicBuffer<u32>>(bad_idx).is_err());
+    }
+}
@@ -1,4 +1,9 @@
 pub mod cyclic_buffer;
+pub mod random;
+pub mod schedule;
+pub mod seed;
 
 pub use cyclic_buffer::Cycle;
 pub use cyclic_buffer::CyclicBuffer;
+pub use schedule::Schedule;
+pub use seed::Seeder;
@@ -0,0 +1,37 @@
+use random_fast_rng::Random;
+
+/// Returns a random number in the range `[0, 1)`
+pub fn uniform<R: Random>(rng: &mut R) -> f32 {
+    (rng.get_u32() >> 8) as f32 / (1u32 << 24) as f32
+}
+
+/// Returns a random index in the range `0..n`
+pub fn below<R: Random>(rng: &mut R, n: usize) -> usize {
+    assert!(n > 0, "Can't pick from an empty range");
+    (rng.get_u64() % n as u64) as usize
+}
+
+/// Sample from the standard normal distribution
+pub fn normal<R: Random>(rng: &mut R) -> f32 {
+    // Box-Muller transform, `u1` must not be zero
+    let u1 = 1. - uniform(rng);
+    let u2 = uniform(rng);
+    (-2. * u1.ln()).sqrt() * (2. * std::f32::consts::PI * u2).cos()
+}
+
+/// Sample an index from a discrete distribution. The weights don't have to be normalized.
+pub fn sample<R: Random>(rng: &mut R, weights: &[f32]) -> usize {
+    let total: f32 = weights.iter().sum();
+    let mut x = uniform(rng) * total;
+    for (i, w) in weights.iter().enumerate() {
+        if x < *w {
+            return i;
+        }
+        x -= w;
+    }
+    // rounding errors can make us overshoot
+    weights
+        .iter()
+        .rposition(|w| *w > 0.)
+        .expect("Can't sample from an empty distribution")
+}
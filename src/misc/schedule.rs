@@ -0,0 +1,173 @@
+/// A value which changes over the course of training, like the exploration rate or the learning rate.
+/// `t` is the number of steps the agent has taken, which for `QAgent` only start counting once its buffer is full.
+/// Closures taking `t` are schedules too.
+pub trait Schedule {
+    fn value(&mut self, t: usize) -> f32;
+
+    /// Ramp up linearly from zero for `steps` steps before starting this schedule
+    fn with_warmup(self, steps: usize) -> Warmup<Self>
+    where
+        Self: Sized,
+    {
+        Warmup { steps, inner: self }
+    }
+}
+
+impl<F: FnMut(usize) -> f32> Schedule for F {
+    fn value(&mut self, t: usize) -> f32 {
+        self(t)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Constant(pub f32);
+
+impl Schedule for Constant {
+    fn value(&mut self, _t: usize) -> f32 {
+        self.0
+    }
+}
+
+/// Move linearly from `start` to `end` over `steps` steps, then stay at `end`
+#[derive(Clone, Copy, Debug)]
+pub struct Linear {
+    pub start: f32,
+    pub end: f32,
+    pub steps: usize,
+}
+
+impl Schedule for Linear {
+    fn value(&mut self, t: usize) -> f32 {
+        if t >= self.steps {
+            self.end
+        } else {
+            self.start + (self.end - self.start) * t as f32 / self.steps as f32
+        }
+    }
+}
+
+/// Multiply `start` by `rate` every step, but never go below `min`
+#[derive(Clone, Copy, Debug)]
+pub struct Exponential {
+    pub start: f32,
+    pub rate: f32,
+    pub min: f32,
+}
+
+impl Schedule for Exponential {
+    fn value(&mut self, t: usize) -> f32 {
+        (self.start * self.rate.powf(t as f32)).max(self.min)
+    }
+}
+
+/// Interpolate linearly between `(t, value)` points, which have to be sorted by `t`.
+/// The value stays constant before the first and after the last point.
+#[derive(Clone, Debug)]
+pub struct Piecewise {
+    points: Vec<(usize, f32)>,
+}
+
+impl Piecewise {
+    pub fn new(points: Vec<(usize, f32)>) -> Self {
+        assert!(!points.is_empty(), "Piecewise schedule needs at least one point");
+        assert!(
+            points.windows(2).all(|w| w[0].0 <= w[1].0),
+            "Points aren't sorted"
+        );
+        Self { points }
+    }
+}
+
+impl Schedule for Piecewise {
+    fn value(&mut self, t: usize) -> f32 {
+        let next = self.points.iter().position(|(x, _)| *x > t);
+        match next {
+            Some(0) => self.points[0].1,
+            Some(i) => {
+                let (x0, y0) = self.points[i - 1];
+                let (x1, y1) = self.points[i];
+                y0 + (y1 - y0) * (t - x0) as f32 / (x1 - x0) as f32
+            }
+            None => self.points[self.points.len() - 1].1,
+        }
+    }
+}
+
+/// Move from `start` to `end` along half a cosine wave over `steps` steps, then stay at `end`
+#[derive(Clone, Copy, Debug)]
+pub struct Cosine {
+    pub start: f32,
+    pub end: f32,
+    pub steps: usize,
+}
+
+impl Schedule for Cosine {
+    fn value(&mut self, t: usize) -> f32 {
+        let progress = t.min(self.steps) as f32 / self.steps.max(1) as f32;
+        let cos = (std::f32::consts::PI * progress).cos();
+        self.end + (self.start - self.end) * (1. + cos) / 2.
+    }
+}
+
+/// Ramps up linearly from zero to the first value of the inner schedule,
+/// which then runs as if it started at the end of the warmup
+#[derive(Clone, Copy, Debug)]
+pub struct Warmup<S> {
+    pub steps: usize,
+    pub inner: S,
+}
+
+impl<S: Schedule> Schedule for Warmup<S> {
+    fn value(&mut self, t: usize) -> f32 {
+        if t < self.steps {
+            self.inner.value(0) * t as f32 / self.steps as f32
+        } else {
+            self.inner.value(t - self.steps)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn piecewise_interpolates_between_points() {
+        let mut schedule = Piecewise::new(vec![(10, 1.), (20, 0.), (30, 0.5)]);
+        assert_eq!(schedule.value(0), 1.);
+        assert_eq!(schedule.value(10), 1.);
+        assert_eq!(schedule.value(15), 0.5);
+        assert_eq!(schedule.value(20), 0.);
+        assert_eq!(schedule.value(25), 0.25);
+        assert_eq!(schedule.value(30), 0.5);
+        assert_eq!(schedule.value(100), 0.5);
+    }
+
+    #[test]
+    fn piecewise_with_a_single_point_is_constant() {
+        let mut schedule = Piecewise::new(vec![(5, 0.3)]);
+        assert_eq!(schedule.value(0), 0.3);
+        assert_eq!(schedule.value(50), 0.3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Points aren't sorted")]
+    fn piecewise_needs_sorted_points() {
+        Piecewise::new(vec![(10, 1.), (5, 0.)]);
+    }
+
+    #[test]
+    fn warmup_ramps_up_to_the_inner_schedule() {
+        let mut schedule = Linear {
+            start: 1.,
+            end: 0.,
+            steps: 10,
+        }
+        .with_warmup(4);
+        assert_eq!(schedule.value(0), 0.);
+        assert_eq!(schedule.value(2), 0.5);
+        assert_eq!(schedule.value(4), 1.);
+        assert_eq!(schedule.value(9), 0.5);
+        assert_eq!(schedule.value(100), 0.);
+    }
+}
@@ -0,0 +1,65 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Derives a stream of independent seeds from a single master seed with splitmix64,
+/// so a whole training run can be reproduced from one number.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Seeder {
+    state: u64,
+}
+
+impl Seeder {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_seed(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a seeder with its own stream, for handing to components which need several seeds
+    pub fn fork(&mut self) -> Seeder {
+        Seeder::new(self.next_seed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_stream() {
+        let mut a = Seeder::new(42);
+        let mut b = Seeder::new(42);
+        for _ in 0..10 {
+            assert_eq!(a.next_seed(), b.next_seed());
+        }
+    }
+
+    #[test]
+    fn seeds_are_distinct() {
+        let mut seeder = Seeder::new(0);
+        let mut seeds = (0..1000).map(|_| seeder.next_seed()).collect::<Vec<_>>();
+        seeds.sort_unstable();
+        seeds.dedup();
+        assert_eq!(seeds.len(), 1000);
+        assert_ne!(Seeder::new(0).next_seed(), Seeder::new(1).next_seed());
+    }
+
+    #[test]
+    fn forks_have_their_own_stream() {
+        let mut seeder = Seeder::new(7);
+        let mut fork = seeder.fork();
+        let mut copy = Seeder::new(7);
+        copy.next_seed();
+        let main = (0..10).map(|_| copy.next_seed()).collect::<Vec<_>>();
+        assert!((0..10).all(|_| !main.contains(&fork.next_seed())));
+        // forking consumes a single seed of the parent
+        assert_eq!(seeder.next_seed(), main[0]);
+    }
+}
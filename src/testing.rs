@@ -0,0 +1,164 @@
+//! Checks which new enviroments can run from a single test, like
+//! `testing::check_enviroment(|| MyGameBuilder::new(), &Conformance::default())`.
+//! Failures panic with a description of the broken invariant.
+
+use random_fast_rng::{FastRng, Random};
+
+use crate::enviroment::{
+    discrete::{ActionToken, TaggedDiscrete},
+    EnvBuilder, Enviroment, GetToken, IsTerminal, PlayerRange,
+};
+use crate::misc::random;
+
+use std::fmt::Debug;
+
+#[cfg(test)]
+pub(crate) mod doubles;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Conformance {
+    /// The number of random episodes to play
+    pub episodes: usize,
+    /// Episodes which take more steps than this fail the check
+    pub max_steps: usize,
+    /// Seed of the random play and of the enviroments
+    pub seed: u64,
+    /// Whether episodes only depend on the actions, so that an enviroment has to behave the same
+    /// after a reset as a freshly built one. Enviroments which draw from their seed every episode can't.
+    pub deterministic: bool,
+}
+
+impl Default for Conformance {
+    fn default() -> Self {
+        Self {
+            episodes: 100,
+            max_steps: 10_000,
+            seed: 0,
+            deterministic: true,
+        }
+    }
+}
+
+/// Plays random episodes in enviroments built by `make` and checks that
+/// - the player to move has a valid action after `reset` and after every non-terminal step
+/// - the actions picked at random among the ones of the players' `ActionToken`s which `validate` accepts can be stepped and give a finite reward
+/// - a terminal status is reached within `max_steps` steps
+/// - two enviroments with the same seed produce the same statuses and rewards for the same actions, episode after episode
+/// - if `deterministic` is set, the same actions produce the same statuses and rewards in a fresh enviroment and after a reset.
+///
+/// `make` has to return identical builders every time, which all get the same seed. Players get their tokens in order and take turns like in `Manager` with a fixed turn order.
+pub fn check_enviroment<B, F>(make: F, config: &Conformance)
+where
+    F: Fn() -> B,
+    B: EnvBuilder + GetToken<Token = ActionToken> + PlayerRange,
+    B::Output: Enviroment<Action = TaggedDiscrete>,
+    <B::Output as Enviroment>::Status: IsTerminal + PartialEq + Debug,
+{
+    let (mut env, tokens) = build(&make, config.seed);
+    let (mut twin, twin_tokens) = build(&make, config.seed);
+    let mut rng = FastRng::seed(config.seed, 0);
+    for episode in 0..config.episodes {
+        let seed = rng.get_u64();
+        let played = play(&mut env, &tokens, seed, config.max_steps, episode);
+        let replayed = play(&mut twin, &twin_tokens, seed, config.max_steps, episode);
+        assert_eq!(
+            played, replayed,
+            "Episode {}: two enviroments with the same seed behaved differently",
+            episode
+        );
+
+        if config.deterministic {
+            let (mut fresh, fresh_tokens) = build(&make, config.seed);
+            let replayed = play(&mut fresh, &fresh_tokens, seed, config.max_steps, episode);
+            assert_eq!(
+                played, replayed,
+                "Episode {}: a fresh enviroment behaved differently",
+                episode
+            );
+
+            let replayed = play(&mut env, &tokens, seed, config.max_steps, episode);
+            assert_eq!(
+                played, replayed,
+                "Episode {}: the enviroment behaved differently after a reset",
+                episode
+            );
+            // keep the twin in step with the enviroment
+            play(&mut twin, &twin_tokens, seed, config.max_steps, episode);
+        }
+    }
+}
+
+fn build<B, F>(make: &F, seed: u64) -> (B::Output, Vec<ActionToken>)
+where
+    F: Fn() -> B,
+    B: EnvBuilder + GetToken<Token = ActionToken> + PlayerRange,
+{
+    let mut builder = make();
+    builder.set_seed(seed);
+    let tokens = (0..B::MIN.max(1))
+        .map(|_| builder.get_token())
+        .collect::<Vec<_>>();
+    (builder.build(), tokens)
+}
+
+/// Plays a single episode and returns the action, status and reward of every step
+fn play<E>(
+    env: &mut E,
+    tokens: &[ActionToken],
+    seed: u64,
+    max_steps: usize,
+    episode: usize,
+) -> Vec<(u32, E::Status, f32)>
+where
+    E: Enviroment<Action = TaggedDiscrete>,
+    E::Status: IsTerminal,
+{
+    let mut rng = FastRng::seed(seed, 0);
+    let mut steps = Vec::new();
+    env.reset();
+    for step in 0..max_steps {
+        let token = &tokens[step % tokens.len()];
+        let valid = token
+            .into_iter()
+            .filter(|action| env.validate(*action))
+            .collect::<Vec<_>>();
+        assert!(
+            !valid.is_empty(),
+            "Episode {}: player {} has no valid action at step {}",
+            episode,
+            token.player(),
+            step
+        );
+
+        let action = valid[random::below(&mut rng, valid.len())];
+        let (status, reward) = env.step(action);
+        assert!(
+            reward.is_finite(),
+            "Episode {}: step {} gave the reward of {}",
+            episode,
+            step,
+            reward
+        );
+
+        let terminal = status.is_terminal();
+        steps.push((action.action, status, reward));
+        if terminal {
+            return steps;
+        }
+    }
+    panic!(
+        "Episode {}: no terminal status within {} steps",
+        episode, max_steps
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::tic_tac_toe::TicTacToeBuilder;
+
+    #[test]
+    fn tic_tac_toe() {
+        check_enviroment(TicTacToeBuilder::new, &Conformance::default());
+    }
+}
@@ -0,0 +1,477 @@
+//! Small deterministic enviroments, agents and networks for the tests of the crate
+
+use crate::agent::{learning::LearningRate, Agent, AgentBuilder, Freeze};
+use crate::enviroment::{
+    discrete::{ActionToken, TaggedDiscrete},
+    AssignRewards, EnvBuilder, Enviroment, GetToken, IsTerminal, Observe, Observed, PlayerRange,
+    Simultaneous, Validate,
+};
+use crate::error::Result;
+use crate::manager::data_collector::DataCollector;
+
+use rusty_nn::network::Network;
+use rusty_nn::optimizer::Optimizer;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use std::cell::RefCell;
+use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Status {
+    Running,
+    Done,
+}
+
+impl IsTerminal for Status {
+    fn is_terminal(&self) -> bool {
+        *self == Status::Done
+    }
+}
+
+/// Players add their action, zero or one, to their own total and are rewarded with it.
+/// The game ends after `len` moves and whoever has the highest total gets the final reward of 1.
+#[derive(Clone, Debug)]
+pub(crate) struct Tally {
+    len: usize,
+    moves: Vec<TaggedDiscrete>,
+    /// The number of tokens handed out by the enviroment itself, for agents built without a `TallyBuilder`
+    players: u32,
+}
+
+impl Tally {
+    pub(crate) fn moves(&self) -> &[TaggedDiscrete] {
+        &self.moves
+    }
+
+    pub(crate) fn total(&self, player: u32) -> u32 {
+        self.moves
+            .iter()
+            .filter(|m| m.player == player)
+            .map(|m| m.action)
+            .sum()
+    }
+
+    fn status(&self) -> Status {
+        if self.moves.len() >= self.len {
+            Status::Done
+        } else {
+            Status::Running
+        }
+    }
+}
+
+/// The state of a `Tally` as seen by agents: a constant input followed by the number of moves made
+pub(crate) fn state(env: &Tally) -> Vec<f32> {
+    vec![1., env.moves.len() as f32]
+}
+
+impl Validate for Tally {
+    type Action = TaggedDiscrete;
+
+    fn validate(&self, action: Self::Action) -> bool {
+        self.status() == Status::Running && action.action <= 1
+    }
+}
+
+impl Enviroment for Tally {
+    type Status = Status;
+
+    fn reset(&mut self) {
+        self.moves.clear();
+    }
+
+    fn step(&mut self, action: Self::Action) -> (Self::Status, f32) {
+        assert!(self.validate(action), "Invalid action {:?}", action);
+        self.moves.push(action);
+        (self.status(), action.action as f32)
+    }
+}
+
+impl AssignRewards for Tally {
+    fn final_rewards(&self, _status: &Self::Status, _last: usize, players: usize) -> Vec<f32> {
+        let totals = (0..players as u32)
+            .map(|player| self.total(player))
+            .collect::<Vec<_>>();
+        let max = totals.iter().copied().max().unwrap_or(0);
+        totals
+            .iter()
+            .map(|total| if *total == max { 1. } else { 0. })
+            .collect()
+    }
+}
+
+/// What a player sees of a `Tally`, which hides the totals of the other players
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Sight {
+    pub(crate) total: u32,
+    pub(crate) moves: usize,
+    running: bool,
+}
+
+impl Validate for Sight {
+    type Action = TaggedDiscrete;
+
+    fn validate(&self, action: Self::Action) -> bool {
+        self.running && action.action <= 1
+    }
+}
+
+impl Observe for Tally {
+    type Observation = Sight;
+
+    fn observe(&self, player: u32) -> Self::Observation {
+        Sight {
+            total: self.total(player),
+            moves: self.moves.len(),
+            running: self.status() == Status::Running,
+        }
+    }
+}
+
+/// All players move at once, so a round takes as many moves as there are players
+impl Simultaneous for Tally {
+    fn step_joint(&mut self, actions: Vec<Self::Action>) -> (Self::Status, Vec<f32>) {
+        for action in actions.iter() {
+            assert!(self.validate(*action), "Invalid action {:?}", action);
+        }
+        self.moves.extend(actions.iter().copied());
+        let rewards = actions.iter().map(|a| a.action as f32).collect();
+        (self.status(), rewards)
+    }
+}
+
+impl GetToken for Tally {
+    type Token = ActionToken;
+
+    fn get_token(&mut self) -> Self::Token {
+        self.players += 1;
+        ActionToken::new(self.players - 1, 1)
+    }
+
+    fn return_token(&mut self, _token: Self::Token) {
+        self.players -= 1;
+    }
+}
+
+/// Builds a `Tally` which ends after `len` moves and hands out tokens in order
+#[derive(Clone, Debug)]
+pub(crate) struct TallyBuilder {
+    len: usize,
+    players: u32,
+}
+
+impl TallyBuilder {
+    pub(crate) fn new(len: usize) -> Self {
+        Self { len, players: 0 }
+    }
+}
+
+impl EnvBuilder for TallyBuilder {
+    type Output = Tally;
+
+    fn build(self) -> Self::Output {
+        Tally {
+            len: self.len,
+            moves: Vec::new(),
+            players: 0,
+        }
+    }
+}
+
+impl GetToken for TallyBuilder {
+    type Token = ActionToken;
+
+    fn get_token(&mut self) -> Self::Token {
+        self.players += 1;
+        ActionToken::new(self.players - 1, 1)
+    }
+
+    fn return_token(&mut self, _token: Self::Token) {
+        self.players -= 1;
+    }
+}
+
+impl PlayerRange for TallyBuilder {
+    const MIN: usize = 1;
+    const MAX: Option<usize> = None;
+}
+
+/// Builds a `Tally` for exactly two players, which refuses to hand out a third token like `TicTacToeBuilder`
+#[derive(Clone, Debug)]
+pub(crate) struct PairBuilder(pub(crate) TallyBuilder);
+
+impl EnvBuilder for PairBuilder {
+    type Output = Tally;
+
+    fn build(self) -> Self::Output {
+        self.0.build()
+    }
+}
+
+impl GetToken for PairBuilder {
+    type Token = ActionToken;
+
+    fn get_token(&mut self) -> Self::Token {
+        assert!(self.0.players < 2, "A pair has two players");
+        self.0.get_token()
+    }
+
+    fn return_token(&mut self, token: Self::Token) {
+        self.0.return_token(token)
+    }
+}
+
+impl PlayerRange for PairBuilder {
+    const MIN: usize = 2;
+    const MAX: Option<usize> = Some(2);
+}
+
+/// What a `Log` was fed, with states made by `state` and the actions without their players
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Entry {
+    Begin(Vec<f32>),
+    Result(Vec<f32>, u32, f32),
+    End,
+}
+
+/// A data collector which shares everything it's fed with the test holding a clone
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Log(Rc<RefCell<Vec<Entry>>>);
+
+impl Log {
+    pub(crate) fn entries(&self) -> Vec<Entry> {
+        self.0.borrow().clone()
+    }
+}
+
+impl DataCollector for Log {
+    type Env = Tally;
+
+    fn begin_episode(&mut self, env: &Self::Env) {
+        self.0.borrow_mut().push(Entry::Begin(state(env)));
+    }
+
+    fn push_result(&mut self, env: &Self::Env, action: TaggedDiscrete, reward: f32) {
+        self.0
+            .borrow_mut()
+            .push(Entry::Result(state(env), action.action, reward));
+    }
+
+    fn end_episode(&mut self, _env: &Self::Env) {
+        self.0.borrow_mut().push(Entry::End);
+    }
+}
+
+/// Plays its actions in order, starting over once they run out
+pub(crate) struct Script {
+    token: ActionToken,
+    actions: Vec<u32>,
+    next: usize,
+}
+
+impl Agent for Script {
+    type Env = Tally;
+    type Data = Log;
+
+    fn action(&mut self, _env: &Self::Env, _data: &Self::Data) -> TaggedDiscrete {
+        let action = self.actions[self.next % self.actions.len()];
+        self.next += 1;
+        self.token
+            .action(action)
+            .expect("Scripted an invalid action")
+    }
+
+    fn update(&mut self, _data: &Self::Data) {}
+
+    fn set_player(&mut self, player: u32) {
+        self.token.set_player(player);
+    }
+}
+
+/// Snapshots play the same script from the start and log to a log of their own
+impl Freeze for Script {
+    type Frozen = Script;
+
+    fn freeze(&self, _data: &Self::Data) -> (Self::Frozen, Log) {
+        let frozen = Script {
+            token: self.token.clone(),
+            actions: self.actions.clone(),
+            next: 0,
+        };
+        (frozen, Log::default())
+    }
+}
+
+/// Builds a `Script` which feeds its experience to `log`
+pub(crate) struct ScriptBuilder {
+    pub(crate) actions: Vec<u32>,
+    pub(crate) log: Log,
+}
+
+/// Builds for a `TallyBuilder` in a manager or a `Tally` in a `Runner`
+impl<G: GetToken<Token = ActionToken>> AgentBuilder<G> for ScriptBuilder {
+    type Data = Log;
+    type Agent = Script;
+
+    fn build(self, env: &mut G) -> Result<(Self::Agent, Self::Data)> {
+        let agent = Script {
+            token: env.get_token(),
+            actions: self.actions,
+            next: 0,
+        };
+        Ok((agent, self.log))
+    }
+}
+
+/// A data collector for observers, which shares every observation it's fed
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Seen(Rc<RefCell<Vec<Sight>>>);
+
+impl Seen {
+    pub(crate) fn sights(&self) -> Vec<Sight> {
+        self.0.borrow().clone()
+    }
+}
+
+impl DataCollector for Seen {
+    type Env = Observed<Sight>;
+
+    fn begin_episode(&mut self, env: &Self::Env) {
+        self.0.borrow_mut().push(env.0.clone());
+    }
+
+    fn push_result(&mut self, env: &Self::Env, _action: TaggedDiscrete, _reward: f32) {
+        self.0.borrow_mut().push(env.0.clone());
+    }
+}
+
+/// An observer which always plays one
+pub(crate) struct Peek {
+    token: ActionToken,
+}
+
+impl Agent for Peek {
+    type Env = Observed<Sight>;
+    type Data = Seen;
+
+    fn action(&mut self, env: &Self::Env, _data: &Self::Data) -> TaggedDiscrete {
+        let action = self.token.action(1).expect("One is a valid action");
+        assert!(env.validate(action), "Observers only act in running games");
+        action
+    }
+
+    fn update(&mut self, _data: &Self::Data) {}
+}
+
+/// Builds a `Peek` which feeds its observations to `seen`
+pub(crate) struct PeekBuilder {
+    pub(crate) seen: Seen,
+}
+
+impl AgentBuilder<TallyBuilder> for PeekBuilder {
+    type Data = Seen;
+    type Agent = Peek;
+
+    fn build(self, env: &mut TallyBuilder) -> Result<(Self::Agent, Self::Data)> {
+        let agent = Peek {
+            token: env.get_token(),
+        };
+        Ok((agent, self.seen))
+    }
+}
+
+/// A network without hidden layers or biases, whose outputs are weighted sums of the inputs
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(crate) struct Linear {
+    inputs: usize,
+    /// Row major, one row of weights for every output
+    weights: Vec<f32>,
+    output: Vec<f32>,
+}
+
+impl Linear {
+    /// A network whose weights are all `weight`
+    pub(crate) fn new(inputs: usize, outputs: usize, weight: f32) -> Self {
+        Self {
+            inputs,
+            weights: vec![weight; inputs * outputs],
+            output: vec![0.; outputs],
+        }
+    }
+}
+
+impl Network for Linear {
+    fn predict(&mut self, input: &[f32]) -> &[f32] {
+        let inputs = self.inputs;
+        for (j, out) in self.output.iter_mut().enumerate() {
+            let row = &self.weights[j * inputs..(j + 1) * inputs];
+            *out = row.iter().zip(input).map(|(w, x)| w * x).sum();
+        }
+        &self.output
+    }
+}
+
+/// Gradient descent on the squared error, summed over the outputs processed since the last update
+#[derive(Clone, Debug)]
+pub(crate) struct Descent {
+    network: Linear,
+    gradients: Vec<f32>,
+    learning_rate: f32,
+}
+
+impl Descent {
+    pub(crate) fn new(network: Linear, learning_rate: f32) -> Self {
+        Self {
+            gradients: vec![0.; network.weights.len()],
+            network,
+            learning_rate,
+        }
+    }
+}
+
+impl Optimizer for Descent {
+    fn process_partial(&mut self, input: &[f32], idx: usize, target: f32) -> f32 {
+        let error = self.network.predict(input)[idx] - target;
+        let inputs = self.network.inputs;
+        for (i, x) in input.iter().enumerate() {
+            self.gradients[idx * inputs + i] += error * x;
+        }
+        0.5 * error * error
+    }
+
+    fn update_model(&mut self) {
+        for (w, g) in self
+            .network
+            .weights
+            .iter_mut()
+            .zip(self.gradients.iter_mut())
+        {
+            *w -= self.learning_rate * *g;
+            *g = 0.;
+        }
+    }
+}
+
+impl LearningRate for Descent {
+    fn set_learning_rate(&mut self, rate: f32) {
+        self.learning_rate = rate;
+    }
+}
+
+impl Deref for Descent {
+    type Target = Linear;
+
+    fn deref(&self) -> &Self::Target {
+        &self.network
+    }
+}
+
+impl DerefMut for Descent {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.network
+    }
+}